@@ -0,0 +1,39 @@
+use tower_lsp::lsp_types::ClientCapabilities;
+
+/// Capabilities announced by the client during `initialize`.
+#[derive(Debug, Default, Clone)]
+pub struct ClientSupport {
+    capabilities: ClientCapabilities,
+}
+
+impl ClientSupport {
+    pub const fn new(capabilities: ClientCapabilities) -> ClientSupport {
+        ClientSupport { capabilities }
+    }
+
+    pub fn hierarchical_document_symbols(&self) -> bool {
+        self.capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.document_symbol.as_ref())
+            .and_then(|document_symbol| document_symbol.hierarchical_document_symbol_support)
+            .unwrap_or(false)
+    }
+
+    pub fn work_done_progress(&self) -> bool {
+        self.capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false)
+    }
+
+    pub fn watched_files_registration(&self) -> bool {
+        self.capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+            .and_then(|watched_files| watched_files.dynamic_registration)
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,27 @@
+/// Names of the commands accepted through `workspace/executeCommand`.
+pub const VALIDATE_LANGUAGE_DEFINITION: &str = "lever.validateLanguageDefinition";
+pub const DIAGNOSE_WORKSPACE: &str = "lever.diagnoseWorkspace";
+pub const SHOW_INCLUDE_GRAPH: &str = "lever.showIncludeGraph";
+pub const FIND_REFERENCES_GROUPED: &str = "lever.findReferencesGrouped";
+pub const REPARSE_FILE: &str = "lever.reparseFile";
+pub const SHOW_CONFIGURATION: &str = "lever.showConfiguration";
+pub const FILE_STATS: &str = "lever.fileStats";
+pub const DUMP_SYMBOL_TABLE: &str = "lever.dumpSymbolTable";
+pub const RUN_PLUGINS: &str = "lever.runPlugins";
+
+pub fn get_commands() -> Vec<String> {
+    [
+        VALIDATE_LANGUAGE_DEFINITION,
+        DIAGNOSE_WORKSPACE,
+        SHOW_INCLUDE_GRAPH,
+        FIND_REFERENCES_GROUPED,
+        REPARSE_FILE,
+        SHOW_CONFIGURATION,
+        FILE_STATS,
+        DUMP_SYMBOL_TABLE,
+        RUN_PLUGINS,
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
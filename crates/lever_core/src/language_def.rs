@@ -6,7 +6,9 @@ use serde::Deserialize;
 use tokio::sync::OnceCell;
 use tower_lsp::lsp_types::{self, SemanticTokensLegend};
 
-use crate::lsp_mappings::{HighlightType, SymbolCompletionType};
+use crate::lsp_mappings::{
+    HighlightType, OutlineType, SymbolCompletionType, UNRESOLVED_TOKEN_TYPE,
+};
 use crate::project::NodeKind;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -20,6 +22,50 @@ pub struct Rule {
     pub import: Import,
     #[serde(default)]
     pub children: Vec<Child>,
+    #[serde(default)]
+    pub completion: Option<CompletionRule>,
+}
+
+// Restricts the completion list inside the nodes of a rule
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CompletionRule {
+    // Kinds of the symbols offered, all symbols are offered if empty
+    #[serde(default)]
+    pub symbol_kinds: Vec<String>,
+    // Names always offered in this context, such as built-in values
+    #[serde(default)]
+    pub extra_items: Vec<String>,
+    pub completion_type: Option<SymbolCompletionType>,
+    // Appended to the inserted names
+    pub insert_suffix: Option<String>,
+    // Names already used in the node aren't offered again, as in a list of table actions
+    #[serde(default)]
+    pub unique: bool,
+    // Characters requesting the completion list inside the nodes, such as `<` for type arguments
+    #[serde(default)]
+    pub trigger_characters: Vec<String>,
+    pub fields: Option<FieldCompletion>,
+}
+
+// Offers the fields of a type instead of symbols, as in a struct initializer
+#[derive(Debug, Deserialize, Clone)]
+pub struct FieldCompletion {
+    // Child whose symbol is the type, or has the type, of the fields, like an assignment target
+    pub target_node: String,
+    // Nodes naming the fields already assigned, which aren't offered again
+    pub field_name_node: String,
+}
+
+impl CompletionRule {
+    pub fn allows(&self, symbol_kind: &str) -> bool {
+        self.symbol_kinds.is_empty() || self.symbol_kinds.iter().any(|kind| kind == symbol_kind)
+    }
+
+    pub fn is_triggered_by(&self, character: &str) -> bool {
+        self.trigger_characters
+            .iter()
+            .any(|trigger| trigger == character)
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -27,6 +73,31 @@ pub struct Child {
     pub query: TreesitterNodeQuery,
     pub rule: DirectOrRule,
     pub highlight_type: Option<HighlightType>,
+    #[serde(default)]
+    pub multiplicity: Multiplicity,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize, Clone, Copy, Default)]
+pub enum Multiplicity {
+    One,
+    Maybe,
+    #[default]
+    Many,
+}
+
+impl Multiplicity {
+    /// Keeps the matches allowed by the multiplicity, or returns None if a required match is
+    /// missing.
+    pub fn select<T>(self, mut matches: Vec<T>) -> Option<Vec<T>> {
+        match self {
+            Multiplicity::One if matches.is_empty() => None,
+            Multiplicity::One | Multiplicity::Maybe => {
+                matches.truncate(1);
+                Some(matches)
+            }
+            Multiplicity::Many => Some(matches),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Deserialize, Clone)]
@@ -73,6 +144,17 @@ pub struct SymbolDef {
     pub name: String,
     pub completion_type: SymbolCompletionType,
     pub highlight_type: HighlightType,
+    // Kind shown in outlines and symbol searches, derived from the completion type by default
+    #[serde(default)]
+    pub outline_type: Option<OutlineType>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BaseType {
+    pub node_name: String,
+    // Shown on hover, `{width}` is replaced by the text of the `width_field` child
+    pub description: String,
+    pub width_field: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -97,6 +179,132 @@ pub struct LanguageDefinition {
     pub symbol_types: Vec<SymbolDef>,
     pub global_ast_rules: Vec<Child>,
     pub ast_rules: Vec<Rule>,
+    // Nodes whose content annotates the symbol declared by their parent (e.g. `@name("...")`)
+    #[serde(default)]
+    pub annotation_nodes: Vec<String>,
+    #[serde(default)]
+    pub base_types: Vec<BaseType>,
+    // Tree-sitter kinds of comments, the ones right above a declaration document its symbol
+    #[serde(default)]
+    pub comment_nodes: Vec<String>,
+    #[serde(default)]
+    pub readonly_symbols: Vec<ReadonlySymbol>,
+    // Nodes assigned to, such as the left-hand side of an assignment
+    #[serde(default)]
+    pub write_nodes: Vec<String>,
+    #[serde(default)]
+    pub call_rules: Vec<CallRule>,
+    // Annotation giving a symbol the name tools know it by, like `name` for `@name("...")`
+    pub name_annotation: Option<String>,
+    // Preprocessor directives completed at the start of a line
+    #[serde(default)]
+    pub directives: Vec<Directive>,
+    #[serde(default)]
+    pub macros: Vec<Macro>,
+    #[serde(default)]
+    pub width_checks: Vec<WidthCheck>,
+    #[serde(default)]
+    pub catch_all_checks: Vec<CatchAllCheck>,
+    // Starts line comments, like `//`, used to comment lines out
+    pub line_comment: Option<String>,
+    // Tree-sitter node kinds skipped when translating children, like punctuation
+    #[serde(default)]
+    pub ignored_nodes: Vec<String>,
+}
+
+// Declarations whose literal value has to fit in the width of their type, as in
+// `const bit<8> x = 256;`
+#[derive(Debug, Deserialize, Clone)]
+pub struct WidthCheck {
+    pub declaration_node: String,
+    // Descendant of the declaration that is the type, like `bit<8>`
+    pub type_node: String,
+    // Descendant of the type holding the width, like `8`
+    pub width_node: String,
+    // Child of the declaration that is the value
+    pub value_node: String,
+    #[serde(default)]
+    pub signed: bool,
+}
+
+// Expressions whose cases should include a catch-all, like a `select` and its `default` case
+#[derive(Debug, Deserialize, Clone)]
+pub struct CatchAllCheck {
+    pub expression_node: String,
+    // Descendant of the expression that is one of its cases
+    pub case_node: String,
+    // Child of the case holding what it matches
+    pub key_node: String,
+    // Keys matching anything, like `default` and `_`
+    pub catch_all_keys: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Directive {
+    pub name: String,
+    // Snippet inserted instead of the name, like `#include "$0"`
+    pub snippet: Option<String>,
+}
+
+// Symbols replaced by text, whose hover shows it, like the macros of `#define`
+#[derive(Debug, Deserialize, Clone)]
+pub struct Macro {
+    pub symbol_kind: String,
+    // Child of the declaration holding the text
+    pub value_node: String,
+}
+
+// Calls whose positional arguments are hinted with the names of the parameters
+#[derive(Debug, Deserialize, Clone)]
+pub struct CallRule {
+    pub call_node: String,
+    // Child whose symbol is the callee, like `apply` in `ipv4_lpm.apply()`
+    pub callee_node: String,
+    pub argument_node: String,
+    // Kind of the symbols declared in the callee's scope that are its parameters
+    pub parameter_kind: String,
+    // Child of the arguments given by name, such as `ttl` in `ttl = 64`
+    pub argument_name_node: Option<String>,
+}
+
+// Symbols that can't be assigned, such as constants or `in` parameters
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReadonlySymbol {
+    pub symbol_kind: String,
+    // Child the declaration needs for its symbol to be read-only, like a direction, with the
+    // given content if any. All the symbols of the kind are read-only without it.
+    pub marker_node: Option<String>,
+    pub marker_content: Option<String>,
+}
+
+fn validate_query(
+    rule_name: &str,
+    query: &TreesitterNodeQuery,
+    language: tree_sitter::Language,
+    problems: &mut Vec<String>,
+) {
+    match query {
+        TreesitterNodeQuery::Path(queries) => {
+            for query in queries {
+                if matches!(query, TreesitterNodeQuery::Path(_)) {
+                    problems.push(format!("{rule_name}: nested paths are not supported"));
+                }
+                validate_query(rule_name, query, language, problems);
+            }
+        }
+        TreesitterNodeQuery::Kind(kind) => {
+            if language.id_for_node_kind(kind, true) == 0
+                && language.id_for_node_kind(kind, false) == 0
+            {
+                problems.push(format!("{rule_name}: unknown node kind `{kind}`"));
+            }
+        }
+        TreesitterNodeQuery::Field(field) => {
+            if language.field_id_for_name(field).is_none() {
+                problems.push(format!("{rule_name}: unknown field `{field}`"));
+            }
+        }
+    }
 }
 
 static INSTANCE: OnceCell<LanguageDefinition> = OnceCell::const_new();
@@ -163,6 +371,8 @@ impl LanguageDefinition {
             }
         }
 
+        symbol_types.push(UNRESOLVED_TOKEN_TYPE);
+
         symbol_types.into_iter().unique().collect_vec()
     }
 
@@ -176,6 +386,39 @@ impl LanguageDefinition {
         self.ast_rules.iter().find(|rule| rule.node_name == name)
     }
 
+    /// Checks that the node kinds and fields queried by the rules exist in the grammar, and that
+    /// every referenced rule is defined. Returns a description of each problem found.
+    pub fn validate(&self, language: tree_sitter::Language) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+
+        for child in &self.global_ast_rules {
+            self.validate_child("global rules", child, language, &mut problems);
+        }
+        for rule in &self.ast_rules {
+            for child in &rule.children {
+                self.validate_child(&rule.node_name, child, language, &mut problems);
+            }
+        }
+
+        problems
+    }
+
+    fn validate_child(
+        &self,
+        rule_name: &str,
+        child: &Child,
+        language: tree_sitter::Language,
+        problems: &mut Vec<String>,
+    ) {
+        validate_query(rule_name, &child.query, language, problems);
+
+        if let DirectOrRule::Rule(name) = &child.rule {
+            if self.rule_with_name(name).is_none() {
+                problems.push(format!("{rule_name}: undefined rule `{name}`"));
+            }
+        }
+    }
+
     pub fn get_semantic_token_types() -> &'static Vec<lsp_types::SemanticTokenType> {
         SEMANTIC_TOKEN_TYPES
             .get()
@@ -189,6 +432,40 @@ impl LanguageDefinition {
         }
     }
 
+    /// Characters triggering completion: `.` for member access, then those of the rules.
+    pub fn get_completion_trigger_characters() -> Vec<String> {
+        let mut characters = vec![String::from(".")];
+        // Directives usually start with a character like `#`, which requests their completion
+        for directive in &LanguageDefinition::get().directives {
+            let Some(first) = directive.name.chars().next() else {
+                continue;
+            };
+            if !first.is_alphanumeric() && !characters.contains(&first.to_string()) {
+                characters.push(first.to_string());
+            }
+        }
+        for rule in &LanguageDefinition::get().ast_rules {
+            let Some(completion) = &rule.completion else {
+                continue;
+            };
+            for character in &completion.trigger_characters {
+                if !characters.contains(character) {
+                    characters.push(character.clone());
+                }
+            }
+        }
+
+        characters
+    }
+
+    /// Whether a completion rule is triggered by the character.
+    pub fn is_rule_trigger_character(&self, character: &str) -> bool {
+        self.ast_rules
+            .iter()
+            .filter_map(|rule| rule.completion.as_ref())
+            .any(|completion| completion.is_triggered_by(character))
+    }
+
     pub fn get_scope_nodes() -> &'static Vec<NodeKind> {
         SCOPE_NODES
             .get()
@@ -201,3 +478,71 @@ impl LanguageDefinition {
             .expect("LanguageDefinition has not been loaded.")
     }
 }
+
+/// Loads a small language definition for the tests that build symbol tables.
+#[cfg(test)]
+pub fn load_test_definition() {
+    static LOADED: std::sync::Once = std::sync::Once::new();
+
+    LOADED.call_once(|| {
+        LanguageDefinition::load(
+            r#"(
+                language: (
+                    name: "Test",
+                    file_extensions: ["test"],
+                    library_paths: (env_variables: [], linux: [], windows: [], macos: []),
+                ),
+                keywords: ["control", "apply"],
+                symbol_types: [
+                    (name: "Control", completion_type: Class, highlight_type: Class),
+                    (name: "Type", completion_type: TypeParameter, highlight_type: TypeParameter),
+                    (name: "Parameter", completion_type: Variable, highlight_type: Parameter),
+                    (
+                        name: "Action",
+                        completion_type: Function,
+                        highlight_type: Function,
+                        outline_type: Some(Method),
+                    ),
+                    (name: "Instance", completion_type: Variable, highlight_type: Variable),
+                ],
+                global_ast_rules: [],
+                ast_rules: [
+                    (node_name: "Root"),
+                    (node_name: "Control", is_scope: true),
+                    (node_name: "Error", is_scope: true),
+                    (node_name: "Package", is_scope: true),
+                    (node_name: "Method", is_scope: true),
+                    (node_name: "Extern", is_scope: true),
+                    (node_name: "Struct", is_scope: true),
+                    (
+                        node_name: "Select",
+                        completion: Some((
+                            symbol_kinds: ["Parameter", "Instance"],
+                            completion_type: Some(Variable),
+                        )),
+                    ),
+                ],
+            )"#,
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Multiplicity;
+
+    #[test]
+    fn test_multiplicities() {
+        assert_eq!(Multiplicity::One.select::<u32>(vec![]), None);
+        assert_eq!(Multiplicity::One.select(vec![1, 2]), Some(vec![1]));
+
+        assert_eq!(Multiplicity::Maybe.select::<u32>(vec![]), Some(vec![]));
+        assert_eq!(Multiplicity::Maybe.select(vec![1, 2]), Some(vec![1]));
+
+        assert_eq!(Multiplicity::Many.select::<u32>(vec![]), Some(vec![]));
+        assert_eq!(
+            Multiplicity::Many.select(vec![1, 2, 3]),
+            Some(vec![1, 2, 3])
+        );
+    }
+}
@@ -1,10 +1,21 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
 use std::sync::RwLock;
 
-use crate::language_def::{self, LanguageDefinition};
+use crate::client_support::ClientSupport;
+use crate::commands;
+use crate::language_def::LanguageDefinition;
 use crate::plugin_manager::{self, OnState, PluginManager, PluginsResult};
 use crate::project::workspace::{FileManagement, LanguageActions, Workspace};
-use tower_lsp::jsonrpc::Result;
+use crate::project::ColorData;
+use serde_json::Value;
+use tower_lsp::jsonrpc::{Error, Result};
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::{
+    GotoDeclarationParams, GotoDeclarationResponse, GotoTypeDefinitionParams,
+    GotoTypeDefinitionResponse, WorkDoneProgressCreate,
+};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
@@ -14,10 +25,17 @@ use simplelog::*;
 
 use std::fs::File;
 
+// Handlers own the locks: each takes the locks it needs itself, one at a time and for a single
+// block, then passes references down. Helpers, the workspace's methods included, never lock the
+// backend's state again since its locks aren't reentrant, and no guard is held across an await.
 pub struct Backend {
     client: Client,
     workspace: RwLock<Workspace>,
     plugin_manager: RwLock<PluginManager>,
+    client_support: RwLock<ClientSupport>,
+    tree_sitter_language: tree_sitter::Language,
+    // Legend sent at initialization, the language's types followed by those of the plugins
+    semantic_token_types: RwLock<Vec<SemanticTokenType>>,
 }
 
 impl Backend {
@@ -26,6 +44,9 @@ impl Backend {
             client,
             workspace: Workspace::new(ts_language).into(),
             plugin_manager: PluginManager::new().into(),
+            client_support: ClientSupport::default().into(),
+            tree_sitter_language: ts_language,
+            semantic_token_types: RwLock::new(vec![]),
         }
     }
 
@@ -33,6 +54,121 @@ impl Backend {
         let client = self.client.clone();
         tokio::spawn(async move { client.publish_diagnostics(uri, diags, None).await });
     }
+
+    /// Publishes the diagnostics of many files, given one file at a time by `get_diagnostics`,
+    /// reporting the progress as `title`.
+    async fn publish_all_diagnostics(
+        &self,
+        token: &str,
+        title: &str,
+        urls: Vec<Url>,
+        mut get_diagnostics: impl FnMut(&Url) -> Vec<Diagnostic>,
+    ) {
+        let token = self.begin_progress(token, title).await;
+
+        let file_count = urls.len();
+        let mut problem_count = 0;
+        for (i, url) in urls.into_iter().enumerate() {
+            self.report_progress(
+                token.as_ref(),
+                url.to_string(),
+                (i * 100 / file_count) as u32,
+            )
+            .await;
+
+            let diags = get_diagnostics(&url);
+            problem_count += diags.len();
+            self.publish_diagnostics(url, diags);
+        }
+
+        self.end_progress(
+            token,
+            format!("{problem_count} problems in {file_count} files"),
+        )
+        .await;
+    }
+
+    async fn publish_eager_diagnostics(&self, mut diagnostics: HashMap<Url, Vec<Diagnostic>>) {
+        if diagnostics.is_empty() {
+            return;
+        }
+
+        let urls = diagnostics.keys().cloned().collect();
+        self.publish_all_diagnostics(
+            "eagerDiagnostics",
+            "Diagnosing included files",
+            urls,
+            |url| diagnostics.remove(url).unwrap_or_default(),
+        )
+        .await;
+    }
+
+    /// Starts reporting progress to the client, if it supports it.
+    async fn begin_progress(&self, token: &str, title: &str) -> Option<NumberOrString> {
+        if !self.client_support.read().unwrap().work_done_progress() {
+            return None;
+        }
+
+        let token = NumberOrString::String(token.to_string());
+        self.client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .ok()?;
+
+        self.send_progress(
+            &token,
+            WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: title.to_string(),
+                percentage: Some(0),
+                ..Default::default()
+            }),
+        )
+        .await;
+
+        Some(token)
+    }
+
+    async fn report_progress(
+        &self,
+        token: Option<&NumberOrString>,
+        message: String,
+        percentage: u32,
+    ) {
+        if let Some(token) = token {
+            self.send_progress(
+                token,
+                WorkDoneProgress::Report(WorkDoneProgressReport {
+                    message: Some(message),
+                    percentage: Some(percentage),
+                    ..Default::default()
+                }),
+            )
+            .await;
+        }
+    }
+
+    async fn end_progress(&self, token: Option<NumberOrString>, message: String) {
+        if let Some(token) = token {
+            self.send_progress(
+                &token,
+                WorkDoneProgress::End(WorkDoneProgressEnd {
+                    message: Some(message),
+                }),
+            )
+            .await;
+        }
+    }
+
+    async fn send_progress(&self, token: &NumberOrString, progress: WorkDoneProgress) {
+        self.client
+            .send_notification::<Progress>(ProgressParams {
+                token: token.clone(),
+                value: ProgressParamsValue::WorkDone(progress),
+            })
+            .await;
+    }
 }
 
 #[tower_lsp::async_trait]
@@ -57,6 +193,8 @@ impl LanguageServer for Backend {
             error!("{info}");
         }));
 
+        *self.client_support.write().unwrap() = ClientSupport::new(params.capabilities.clone());
+
         if let Some(root_uri) = params.root_uri.clone() {
             self.workspace
                 .write()
@@ -75,21 +213,48 @@ impl LanguageServer for Backend {
                 .load_plugins(params.root_uri, options.to_string().as_str());
         }
 
+        // Plugins are only loaded here, so the legend and its indices stay the same afterwards
+        let mut token_types = LanguageDefinition::get_semantic_token_types().clone();
+        for token_type in self.plugin_manager.read().unwrap().get_token_types() {
+            let token_type = SemanticTokenType::from(token_type);
+            if !token_types.contains(&token_type) {
+                token_types.push(token_type);
+            }
+        }
+        *self.semantic_token_types.write().unwrap() = token_types.clone();
+
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
                 semantic_tokens_provider: Some(
                     SemanticTokensServerCapabilities::SemanticTokensOptions(
                         SemanticTokensOptions {
                             range: Some(false),
-                            legend: language_def::LanguageDefinition::get_semantic_token_legend(),
+                            legend: SemanticTokensLegend {
+                                token_types,
+                                token_modifiers: vec![],
+                            },
                             full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
                             ..Default::default()
                         },
                     ),
                 ),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                references_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
                 completion_provider: Some(CompletionOptions {
-                    trigger_characters: Some(vec![String::from(".")]),
+                    trigger_characters: Some(
+                        LanguageDefinition::get_completion_trigger_characters(),
+                    ),
                     ..Default::default()
                 }),
                 text_document_sync: Some(TextDocumentSyncCapability::Options(
@@ -101,9 +266,15 @@ impl LanguageServer for Backend {
                         save: Some(TextDocumentSyncSaveOptions::Supported(true)),
                     },
                 )),
+                declaration_provider: Some(DeclarationCapability::Simple(true)),
                 definition_provider: Some(OneOf::Left(true)),
+                type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: commands::get_commands(),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
                 rename_provider: Some(OneOf::Right(RenameOptions {
-                    prepare_provider: Some(false),
+                    prepare_provider: Some(true),
                     work_done_progress_options: WorkDoneProgressOptions::default(),
                 })),
                 workspace: Some(WorkspaceServerCapabilities {
@@ -121,6 +292,40 @@ impl LanguageServer for Backend {
 
     async fn initialized(&self, _: InitializedParams) {
         info!("Lsp initialized");
+
+        if !self
+            .client_support
+            .read()
+            .unwrap()
+            .watched_files_registration()
+        {
+            return;
+        }
+
+        let watchers = LanguageDefinition::get()
+            .language
+            .file_extensions
+            .iter()
+            .map(|extension| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(format!(
+                    "**/*.{}",
+                    extension.trim_start_matches('.')
+                )),
+                kind: None,
+            })
+            .collect();
+        let registration = Registration {
+            id: String::from("watched-files"),
+            method: String::from("workspace/didChangeWatchedFiles"),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        };
+
+        if let Err(err) = self.client.register_capability(vec![registration]).await {
+            warn!("Failed to register file watchers: {err}");
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -132,13 +337,40 @@ impl LanguageServer for Backend {
         let doc = params.text_document;
         info!("Opening file: {}", doc.uri);
 
-        let mut diagnostics = {
+        let (mut diagnostics, indexed, eager_diagnostics) = {
             let mut workspace = self.workspace.write().unwrap();
+            let includes = workspace.get_include_edges();
             workspace.add_file(doc.uri.clone(), &doc.text);
-
-            workspace.get_full_diagnostics(&doc.uri)
+            workspace.set_file_open(&doc.uri, true);
+
+            let indexed = workspace
+                .get_file(&doc.uri)
+                .is_none_or(|file| file.is_indexed());
+            let eager_diagnostics = if workspace.get_include_edges() == includes {
+                HashMap::new()
+            } else {
+                workspace.get_eager_diagnostics()
+            };
+
+            (
+                workspace.get_full_diagnostics(&doc.uri),
+                indexed,
+                eager_diagnostics,
+            )
         };
 
+        if !indexed {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!(
+                        "{} is too large or failed to parse, its symbols won't be indexed.",
+                        doc.uri
+                    ),
+                )
+                .await;
+        }
+
         let mut plugin_result: PluginsResult = self
             .plugin_manager
             .write()
@@ -148,17 +380,54 @@ impl LanguageServer for Backend {
         diagnostics.append(&mut plugin_result.diagnostic);
 
         self.publish_diagnostics(doc.uri, diagnostics);
+        self.publish_eager_diagnostics(eager_diagnostics).await;
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        info!("Closing file: {}", params.text_document.uri);
+
+        let mut workspace = self.workspace.write().unwrap();
+        workspace.set_file_open(&params.text_document.uri, false);
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let diagnostics = {
+        let (diagnostics, eager_diagnostics, dependent_diagnostics) = {
             let mut workspace = self.workspace.write().unwrap();
-            workspace.update_file(&params.text_document.uri, params.content_changes);
+            let includes = workspace.get_include_edges();
+            let exported_symbols = workspace.get_exported_symbols(&params.text_document.uri);
+            if workspace
+                .update_file(&params.text_document.uri, params.content_changes)
+                .is_none()
+            {
+                return;
+            }
 
-            workspace.get_quick_diagnostics(&params.text_document.uri)
+            // Files including this one only need linking again when what it exports changes
+            let dependent_diagnostics =
+                if workspace.get_exported_symbols(&params.text_document.uri) == exported_symbols {
+                    HashMap::new()
+                } else {
+                    workspace.refresh_dependents(&params.text_document.uri)
+                };
+            let mut eager_diagnostics = if workspace.get_include_edges() == includes {
+                HashMap::new()
+            } else {
+                workspace.get_eager_diagnostics()
+            };
+            // Dependents already get their full diagnostics
+            eager_diagnostics.retain(|url, _| !dependent_diagnostics.contains_key(url));
+            (
+                workspace.get_quick_diagnostics(&params.text_document.uri),
+                eager_diagnostics,
+                dependent_diagnostics,
+            )
         };
 
         self.publish_diagnostics(params.text_document.uri, diagnostics);
+        for (url, diags) in dependent_diagnostics {
+            self.publish_diagnostics(url, diags);
+        }
+        self.publish_eager_diagnostics(eager_diagnostics).await;
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
@@ -185,20 +454,89 @@ impl LanguageServer for Backend {
         self.publish_diagnostics(params.text_document.uri, diagnostics);
     }
 
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let diagnostics: Vec<(Url, Vec<Diagnostic>)> = {
+            let mut workspace = self.workspace.write().unwrap();
+
+            let mut updated_urls: Vec<Url> = vec![];
+            for change in params.changes {
+                let urls = if change.typ == FileChangeType::DELETED {
+                    workspace.remove_deleted_file(&change.uri)
+                } else {
+                    workspace.reload_file(&change.uri)
+                };
+                for url in urls {
+                    if !updated_urls.contains(&url) {
+                        updated_urls.push(url);
+                    }
+                }
+            }
+
+            updated_urls
+                .into_iter()
+                .map(|url| {
+                    let diagnostics = workspace.get_full_diagnostics(&url);
+                    (url, diagnostics)
+                })
+                .collect()
+        };
+
+        for (url, diags) in diagnostics {
+            self.publish_diagnostics(url, diags);
+        }
+    }
+
+    async fn goto_declaration(
+        &self,
+        params: GotoDeclarationParams,
+    ) -> Result<Option<GotoDeclarationResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+
+        let maybe_location = {
+            let workspace = self.workspace.read().unwrap();
+
+            workspace.get_declaration_location(&uri, params.text_document_position_params.position)
+        };
+
+        maybe_location.map_or(Ok(None), |location| {
+            Ok(Some(GotoDeclarationResponse::Scalar(location)))
+        })
+    }
+
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
         let uri = params.text_document_position_params.text_document.uri;
 
+        let mut locations = {
+            let workspace = self.workspace.read().unwrap();
+
+            workspace.get_definition_locations(&uri, params.text_document_position_params.position)
+        };
+
+        Ok(match locations.len() {
+            0 => None,
+            1 => locations.pop().map(GotoDefinitionResponse::Scalar),
+            _ => Some(GotoDefinitionResponse::Array(locations)),
+        })
+    }
+
+    async fn goto_type_definition(
+        &self,
+        params: GotoTypeDefinitionParams,
+    ) -> Result<Option<GotoTypeDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+
         let maybe_location = {
             let workspace = self.workspace.read().unwrap();
 
-            workspace.get_definition_location(&uri, params.text_document_position_params.position)
+            workspace
+                .get_type_definition_location(&uri, params.text_document_position_params.position)
         };
 
         maybe_location.map_or(Ok(None), |location| {
-            Ok(Some(GotoDefinitionResponse::Scalar(location)))
+            Ok(Some(GotoTypeDefinitionResponse::Scalar(location)))
         })
     }
 
@@ -220,20 +558,129 @@ impl LanguageServer for Backend {
         })
     }
 
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace.get_references(
+            &params.text_document_position.text_document.uri,
+            params.text_document_position.position,
+            params.context.include_declaration,
+        ))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace.get_document_highlights(
+            &params.text_document_position_params.text_document.uri,
+            params.text_document_position_params.position,
+        ))
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace.get_signature_help(
+            &params.text_document_position_params.text_document.uri,
+            params.text_document_position_params.position,
+        ))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace.get_inlay_hints(&params.text_document.uri, params.range))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace.get_code_actions(&params.text_document.uri, params.range))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(Some(workspace.get_workspace_symbols(&params.query)))
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let hierarchical = self
+            .client_support
+            .read()
+            .unwrap()
+            .hierarchical_document_symbols();
+
+        let workspace = self.workspace.read().unwrap();
+        Ok(workspace.get_document_symbols(&params.text_document.uri, hierarchical))
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(Some(
+            workspace.get_folding_ranges(&params.text_document.uri),
+        ))
+    }
+
     async fn semantic_tokens_full(
         &self,
         params: SemanticTokensParams,
     ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri.clone();
+        let plugin_manager = self.plugin_manager.read().unwrap().clone();
+        // Plugins are processes, waiting for them neither blocks the runtime nor the lock
+        let plugin_tokens = tokio::task::spawn_blocking(move || {
+            plugin_manager
+                .run_plugins(&uri, &OnState::SemanticTokens)
+                .tokens
+        })
+        .await
+        .unwrap_or_default();
+        let extra_tokens = {
+            let token_types = self.semantic_token_types.read().unwrap();
+            plugin_tokens
+                .into_iter()
+                .filter_map(|token| {
+                    let node_type = token_types
+                        .iter()
+                        .position(|token_type| token_type.as_str() == token.token_type)?;
+                    Some(ColorData {
+                        line: token.line,
+                        start: token.start,
+                        length: token.length,
+                        node_type: node_type as u32,
+                    })
+                })
+                .collect()
+        };
+
         let response = {
             let workspace = self.workspace.read().unwrap();
 
-            Ok(workspace.get_semantic_tokens(&params.text_document.uri))
+            Ok(workspace.get_semantic_tokens(&params.text_document.uri, extra_tokens))
         };
 
         response
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let uri = params.text_document_position.text_document.uri.clone();
+        let position = params.text_document_position.position;
+        let plugin_manager = self.plugin_manager.read().unwrap().clone();
+        let plugin_items =
+            tokio::task::spawn_blocking(move || plugin_manager.run_completion(&uri, position))
+                .await
+                .unwrap_or_default();
         let completion_list = {
             let workspace = self.workspace.read().unwrap();
 
@@ -242,11 +689,23 @@ impl LanguageServer for Backend {
                     &params.text_document_position.text_document.uri,
                     params.text_document_position.position,
                     params.context,
+                    plugin_items,
                 )
                 .unwrap_or_default()
         };
 
-        Ok(Some(CompletionResponse::Array(completion_list)))
+        Ok(Some(CompletionResponse::List(completion_list)))
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        let workspace = self.workspace.read().unwrap();
+
+        Ok(workspace
+            .prepare_rename(&params.text_document.uri, params.position)
+            .map(PrepareRenameResponse::Range))
     }
 
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
@@ -263,8 +722,133 @@ impl LanguageServer for Backend {
         response
     }
 
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        match params.command.as_str() {
+            commands::VALIDATE_LANGUAGE_DEFINITION => {
+                let problems = LanguageDefinition::get().validate(self.tree_sitter_language);
+                info!("Language definition problems: {:?}", problems);
+
+                Ok(Some(Value::from(problems)))
+            }
+            commands::DIAGNOSE_WORKSPACE => {
+                // Computed file by file, so that the progress follows the slow part
+                let urls = self.workspace.read().unwrap().get_indexed_files();
+                self.publish_all_diagnostics(
+                    commands::DIAGNOSE_WORKSPACE,
+                    "Diagnosing workspace",
+                    urls,
+                    |url| self.workspace.read().unwrap().get_full_diagnostics(url),
+                )
+                .await;
+
+                Ok(None)
+            }
+            commands::SHOW_INCLUDE_GRAPH => {
+                Ok(Some(self.workspace.read().unwrap().get_include_graph()))
+            }
+            commands::FIND_REFERENCES_GROUPED => {
+                let position = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .and_then(|argument| {
+                        serde_json::from_value::<TextDocumentPositionParams>(argument).ok()
+                    })
+                    .ok_or_else(|| {
+                        Error::invalid_params("Expected a text document position argument.")
+                    })?;
+
+                Ok(self
+                    .workspace
+                    .read()
+                    .unwrap()
+                    .get_grouped_references(&position.text_document.uri, position.position))
+            }
+            commands::REPARSE_FILE => {
+                let url = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .and_then(|argument| serde_json::from_value::<Url>(argument).ok())
+                    .ok_or_else(|| Error::invalid_params("Expected a file URI argument."))?;
+
+                let diagnostics = {
+                    let mut workspace = self.workspace.write().unwrap();
+                    if !workspace.reparse_file(&url) {
+                        return Ok(Some(Value::Bool(false)));
+                    }
+                    workspace.get_full_diagnostics(&url)
+                };
+                self.publish_diagnostics(url, diagnostics);
+
+                Ok(Some(Value::Bool(true)))
+            }
+            commands::SHOW_CONFIGURATION => {
+                let workspace = self.workspace.read().unwrap();
+
+                Ok(serde_json::to_value(workspace.get_settings()).ok())
+            }
+            commands::FILE_STATS => {
+                // Without a file argument, the stats of the whole workspace are returned
+                let url = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .and_then(|argument| serde_json::from_value::<Url>(argument).ok());
+
+                Ok(Some(
+                    self.workspace.read().unwrap().get_file_stats(url.as_ref()),
+                ))
+            }
+            commands::DUMP_SYMBOL_TABLE => {
+                let url = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .and_then(|argument| serde_json::from_value::<Url>(argument).ok())
+                    .ok_or_else(|| Error::invalid_params("Expected a file URI argument."))?;
+
+                Ok(self
+                    .workspace
+                    .read()
+                    .unwrap()
+                    .get_symbol_table_text(&url)
+                    .map(Value::from))
+            }
+            commands::RUN_PLUGINS => {
+                let url = params
+                    .arguments
+                    .into_iter()
+                    .next()
+                    .and_then(|argument| serde_json::from_value::<Url>(argument).ok())
+                    .ok_or_else(|| Error::invalid_params("Expected a file URI argument."))?;
+
+                let plugin_manager = self.plugin_manager.read().unwrap().clone();
+                let outputs =
+                    tokio::task::spawn_blocking(move || plugin_manager.inspect_plugins(&url))
+                        .await
+                        .unwrap_or_default();
+                Ok(serde_json::to_value(outputs).ok())
+            }
+            command => Err(Error::invalid_params(format!("Unknown command: {command}"))),
+        }
+    }
+
     async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
-        let mut workspace = self.workspace.write().unwrap();
-        workspace.update_settings(params.settings);
+        let (plugin_paths, root_path, eager_diagnostics) = {
+            let mut workspace = self.workspace.write().unwrap();
+            workspace.update_settings(params.settings);
+            (
+                workspace.get_settings().plugin_paths.clone(),
+                workspace.get_root_path().map(PathBuf::from),
+                workspace.get_eager_diagnostics(),
+            )
+        };
+        self.plugin_manager
+            .write()
+            .unwrap()
+            .load_plugin_dirs(&plugin_paths, root_path.as_deref());
+
+        self.publish_eager_diagnostics(eager_diagnostics).await;
     }
 }
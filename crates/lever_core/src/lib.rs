@@ -1,5 +1,7 @@
 #![warn(clippy::all)]
 #![allow(clippy::cast_possible_truncation, clippy::wildcard_imports)]
+mod client_support;
+mod commands;
 mod language_def;
 mod language_server;
 mod lsp_mappings;
@@ -1,5 +1,5 @@
 use serde::Deserialize;
-use tower_lsp::lsp_types::{self, CompletionItemKind};
+use tower_lsp::lsp_types::{self, CompletionItemKind, SymbolKind};
 
 #[derive(Debug, Deserialize, Clone)]
 pub enum SymbolCompletionType {
@@ -60,8 +60,101 @@ impl SymbolCompletionType {
             Self::TypeParameter => CompletionItemKind::TYPE_PARAMETER,
         }
     }
+
+    pub const fn get_symbol_kind(&self) -> SymbolKind {
+        match self {
+            Self::Text | Self::Snippet => SymbolKind::STRING,
+            Self::Method => SymbolKind::METHOD,
+            Self::Function => SymbolKind::FUNCTION,
+            Self::Constructor => SymbolKind::CONSTRUCTOR,
+            Self::Field => SymbolKind::FIELD,
+            Self::Variable | Self::Reference => SymbolKind::VARIABLE,
+            Self::Class => SymbolKind::CLASS,
+            Self::Interface => SymbolKind::INTERFACE,
+            Self::Module => SymbolKind::MODULE,
+            Self::Property => SymbolKind::PROPERTY,
+            Self::Unit => SymbolKind::NUMBER,
+            Self::Value | Self::Color | Self::Constant => SymbolKind::CONSTANT,
+            Self::Enum => SymbolKind::ENUM,
+            Self::Keyword => SymbolKind::KEY,
+            Self::File => SymbolKind::FILE,
+            Self::Folder => SymbolKind::PACKAGE,
+            Self::EnumMember => SymbolKind::ENUM_MEMBER,
+            Self::Struct => SymbolKind::STRUCT,
+            Self::Event => SymbolKind::EVENT,
+            Self::Operator => SymbolKind::OPERATOR,
+            Self::TypeParameter => SymbolKind::TYPE_PARAMETER,
+        }
+    }
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub enum OutlineType {
+    File,
+    Module,
+    Namespace,
+    Package,
+    Class,
+    Method,
+    Property,
+    Field,
+    Constructor,
+    Enum,
+    Interface,
+    Function,
+    Variable,
+    Constant,
+    String,
+    Number,
+    Boolean,
+    Array,
+    Object,
+    Key,
+    Null,
+    EnumMember,
+    Struct,
+    Event,
+    Operator,
+    TypeParameter,
+}
+
+impl OutlineType {
+    pub const fn get(&self) -> SymbolKind {
+        match self {
+            Self::File => SymbolKind::FILE,
+            Self::Module => SymbolKind::MODULE,
+            Self::Namespace => SymbolKind::NAMESPACE,
+            Self::Package => SymbolKind::PACKAGE,
+            Self::Class => SymbolKind::CLASS,
+            Self::Method => SymbolKind::METHOD,
+            Self::Property => SymbolKind::PROPERTY,
+            Self::Field => SymbolKind::FIELD,
+            Self::Constructor => SymbolKind::CONSTRUCTOR,
+            Self::Enum => SymbolKind::ENUM,
+            Self::Interface => SymbolKind::INTERFACE,
+            Self::Function => SymbolKind::FUNCTION,
+            Self::Variable => SymbolKind::VARIABLE,
+            Self::Constant => SymbolKind::CONSTANT,
+            Self::String => SymbolKind::STRING,
+            Self::Number => SymbolKind::NUMBER,
+            Self::Boolean => SymbolKind::BOOLEAN,
+            Self::Array => SymbolKind::ARRAY,
+            Self::Object => SymbolKind::OBJECT,
+            Self::Key => SymbolKind::KEY,
+            Self::Null => SymbolKind::NULL,
+            Self::EnumMember => SymbolKind::ENUM_MEMBER,
+            Self::Struct => SymbolKind::STRUCT,
+            Self::Event => SymbolKind::EVENT,
+            Self::Operator => SymbolKind::OPERATOR,
+            Self::TypeParameter => SymbolKind::TYPE_PARAMETER,
+        }
+    }
+}
+
+// Token type of the usages that don't resolve to any symbol, not one of the standard types
+pub const UNRESOLVED_TOKEN_TYPE: lsp_types::SemanticTokenType =
+    lsp_types::SemanticTokenType::new("unresolved");
+
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
 pub enum HighlightType {
     Namespace,
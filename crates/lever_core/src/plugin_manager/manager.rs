@@ -1,11 +1,20 @@
+use crate::lsp_mappings::SymbolCompletionType;
 use crate::plugin_manager::notification::CustomParams;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{from_str, to_string};
-use std::io::Write;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 use tower_lsp::lsp_types::Diagnostic;
 use tower_lsp::lsp_types::*;
 
+/// Plugins still running after this long are killed.
+const PLUGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
 pub struct PluginManager {
     plugins: Vec<Plugin>,
 }
@@ -15,12 +24,16 @@ pub enum OnState {
     Save,
     Open,
     Change,
+    SemanticTokens,
+    Completion,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Clone)]
 pub enum TypesNotification {
     Notification,
     Diagnostic,
+    SemanticTokens,
+    Completion,
     Nothing,
 }
 
@@ -31,6 +44,12 @@ pub struct Plugin {
     on: Vec<OnState>,
     arguments: Vec<Argument>,
     state: bool,
+    // Semantic token types added to the legend for the tokens of the plugin
+    #[serde(default)]
+    token_types: Vec<String>,
+    // Loaded from a directory of the settings rather than from the initialization options
+    #[serde(skip)]
+    from_dir: bool,
 }
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Argument {
@@ -44,15 +63,57 @@ pub struct CustomResult {
     data: String,
 }
 
+// Token highlighted by a plugin, its type is one of the plugin's token types
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PluginToken {
+    pub line: u32,
+    pub start: u32,
+    pub length: u32,
+    pub token_type: String,
+}
+
+// Completion item suggested by a plugin, for the position given as its `position` argument
+#[derive(Deserialize, Clone)]
+pub struct PluginCompletion {
+    pub label: String,
+    pub kind: Option<SymbolCompletionType>,
+    pub detail: Option<String>,
+    pub insert_text: Option<String>,
+}
+
+impl PluginCompletion {
+    fn into_item(self) -> CompletionItem {
+        CompletionItem {
+            label: self.label,
+            kind: self.kind.map(|kind| kind.get()),
+            detail: self.detail,
+            insert_text: self.insert_text,
+            ..Default::default()
+        }
+    }
+}
+
+// Raw output of one plugin, or why it has none
+#[derive(Serialize)]
+pub struct PluginOutput {
+    pub name: String,
+    pub output: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
 pub struct PluginsResult {
     pub diagnostic: Vec<Diagnostic>,
     pub notification: Vec<CustomParams>,
+    pub tokens: Vec<PluginToken>,
+    pub completion: Vec<CompletionItem>,
 }
 impl PluginsResult {
     pub fn new() -> PluginsResult {
         PluginsResult {
             diagnostic: Vec::new(),
             notification: Vec::new(),
+            tokens: Vec::new(),
+            completion: Vec::new(),
         }
     }
 }
@@ -65,17 +126,23 @@ impl PluginManager {
     }
     pub fn load_plugins(&mut self, uri: Option<Url>, json_str: &str) {
         if let Ok(mut plugins) = from_str::<Vec<Plugin>>(json_str) {
-            if let Some(url) = uri {
+            // Without a local root, such as for a single file, plugins get no workspace argument
+            let workspace = uri.as_ref().and_then(|url| {
+                let path = url
+                    .to_file_path()
+                    .ok()
+                    .and_then(|path| path.into_os_string().into_string().ok());
+                if path.is_none() {
+                    warn!("Plugins get no workspace, {url} isn't a local path");
+                }
+                path
+            });
+            if let Some(workspace) = workspace {
                 let key = String::from("workspace");
                 for plugin in &mut plugins {
                     plugin.arguments.push(Argument {
                         key: key.clone(),
-                        value: url
-                            .to_file_path()
-                            .unwrap()
-                            .into_os_string()
-                            .into_string()
-                            .unwrap(),
+                        value: workspace.clone(),
                     });
                 }
             }
@@ -84,31 +151,139 @@ impl PluginManager {
         }
     }
 
-    pub fn run_plugins(&mut self, file: &Url, state: &OnState) -> PluginsResult {
+    /// Loads the plugins listed by the JSON files of the directories, in place of those loaded
+    /// from directories before. Relative plugin paths are relative to their directory. The token
+    /// types of these plugins aren't in the legend, which is sent before the settings.
+    pub fn load_plugin_dirs(&mut self, dirs: &[PathBuf], workspace: Option<&Path>) {
+        self.plugins.retain(|plugin| !plugin.from_dir);
+
+        for dir in dirs {
+            info!("Searching plugins in {}", dir.display());
+            let Ok(entries) = fs::read_dir(dir) else {
+                warn!("Failed to read the plugin directory {}", dir.display());
+                continue;
+            };
+            let mut files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "json")
+                })
+                .collect();
+            files.sort();
+
+            for file in files {
+                let plugins = fs::read_to_string(&file)
+                    .map_err(|err| err.to_string())
+                    .and_then(|content| {
+                        from_str::<Vec<Plugin>>(&content).map_err(|err| err.to_string())
+                    });
+                let plugins = match plugins {
+                    Ok(plugins) => plugins,
+                    Err(err) => {
+                        warn!("Ignoring the plugins of {}: {err}", file.display());
+                        continue;
+                    }
+                };
+
+                for mut plugin in plugins {
+                    // Joining an absolute path keeps it as is
+                    plugin.path = dir.join(&plugin.path).to_string_lossy().into_owned();
+                    plugin.from_dir = true;
+                    if let Some(workspace) = workspace {
+                        plugin.arguments.push(Argument {
+                            key: String::from("workspace"),
+                            value: workspace.to_string_lossy().into_owned(),
+                        });
+                    }
+                    self.plugins.push(plugin);
+                }
+            }
+        }
+    }
+
+    /// Token types of the plugins in loading order, which gives them stable legend indices.
+    pub fn get_token_types(&self) -> Vec<String> {
+        let mut token_types: Vec<String> = vec![];
+        for token_type in self.plugins.iter().flat_map(|plugin| &plugin.token_types) {
+            if !token_types.contains(token_type) {
+                token_types.push(token_type.clone());
+            }
+        }
+
+        token_types
+    }
+
+    pub fn run_plugins(&self, file: &Url, state: &OnState) -> PluginsResult {
+        self.run_plugins_with(file, state, &[])
+    }
+
+    /// Runs the completion plugins, which are also given the position to complete.
+    pub fn run_completion(&self, file: &Url, position: Position) -> Vec<CompletionItem> {
+        let position = Argument {
+            key: String::from("position"),
+            value: to_string(&position).unwrap_or_default(),
+        };
+
+        self.run_plugins_with(file, &OnState::Completion, &[position])
+            .completion
+    }
+
+    fn run_plugins_with(
+        &self,
+        file: &Url,
+        state: &OnState,
+        arguments: &[Argument],
+    ) -> PluginsResult {
         let mut plugins_result: PluginsResult = PluginsResult::new();
+        // Plugins are given a path, documents without one like `untitled:` ones are skipped
+        let Ok(path) = file.to_file_path() else {
+            return plugins_result;
+        };
         for plugin in &mut self.plugins.clone() {
             let key = String::from("file");
             plugin.arguments.push(Argument {
                 key: key.clone(),
-                value: file
-                    .to_file_path()
-                    .unwrap()
-                    .into_os_string()
-                    .into_string()
-                    .unwrap(),
+                value: path.to_string_lossy().into_owned(),
             });
+            plugin.arguments.extend(arguments.iter().cloned());
             if plugin.on.contains(state) {
                 let json_str = PluginManager::execute(plugin.clone());
-                let results: CustomResult = from_str(json_str.as_str()).unwrap();
+                let Some(results) = parse_output::<CustomResult>(&plugin.name, &json_str) else {
+                    continue;
+                };
 
                 match results.output_type {
                     TypesNotification::Diagnostic => {
-                        let mut diag: Vec<Diagnostic> = from_str(results.data.as_str()).unwrap();
-                        plugins_result.diagnostic.append(&mut diag);
+                        if let Some(mut diag) =
+                            parse_output::<Vec<Diagnostic>>(&plugin.name, &results.data)
+                        {
+                            plugins_result.diagnostic.append(&mut diag);
+                        }
                     }
                     TypesNotification::Notification => {
-                        let notification: CustomParams = from_str(results.data.as_str()).unwrap();
-                        plugins_result.notification.push(notification);
+                        if let Some(notification) =
+                            parse_output::<CustomParams>(&plugin.name, &results.data)
+                        {
+                            plugins_result.notification.push(notification);
+                        }
+                    }
+                    TypesNotification::SemanticTokens => {
+                        if let Some(mut tokens) =
+                            parse_output::<Vec<PluginToken>>(&plugin.name, &results.data)
+                        {
+                            tokens.retain(|token| plugin.token_types.contains(&token.token_type));
+                            plugins_result.tokens.append(&mut tokens);
+                        }
+                    }
+                    TypesNotification::Completion => {
+                        if let Some(completions) =
+                            parse_output::<Vec<PluginCompletion>>(&plugin.name, &results.data)
+                        {
+                            plugins_result
+                                .completion
+                                .extend(completions.into_iter().map(PluginCompletion::into_item));
+                        }
                     }
                     TypesNotification::Nothing => {}
                 }
@@ -117,25 +292,177 @@ impl PluginManager {
         plugins_result
     }
 
+    /// Runs every plugin on the file whatever its states, and returns what each one printed
+    /// without interpreting it. Output that isn't JSON is returned as a string.
+    pub fn inspect_plugins(&self, file: &Url) -> Vec<PluginOutput> {
+        let Ok(path) = file.to_file_path() else {
+            return vec![];
+        };
+
+        self.plugins
+            .iter()
+            .map(|plugin| {
+                let mut plugin = plugin.clone();
+                plugin.arguments.push(Argument {
+                    key: String::from("file"),
+                    value: path.to_string_lossy().into_owned(),
+                });
+
+                match PluginManager::try_execute(&plugin, PLUGIN_TIMEOUT) {
+                    Ok(output) => PluginOutput {
+                        name: plugin.name,
+                        output: Some(
+                            from_str(&output).unwrap_or(serde_json::Value::String(output)),
+                        ),
+                        error: None,
+                    },
+                    Err(error) => PluginOutput {
+                        name: plugin.name,
+                        output: None,
+                        error: Some(error),
+                    },
+                }
+            })
+            .collect()
+    }
+
     fn execute(plugin: Plugin) -> String {
-        // Replace "your_program" with the actual binary you want to execute
-        let mut child = Command::new(plugin.path.clone())
+        PluginManager::try_execute(&plugin, PLUGIN_TIMEOUT).unwrap_or_else(|error| {
+            error!("Plugin {} failed: {error}", plugin.name);
+            error
+        })
+    }
+
+    /// Writes the arguments of the plugin to its stdin and returns what it printed on stdout,
+    /// or why it failed, including running for longer than `timeout`.
+    fn try_execute(plugin: &Plugin, timeout: Duration) -> Result<String, String> {
+        let mut child = Command::new(&plugin.path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .spawn()
-            .unwrap();
+            .map_err(|err| format!("Failed to start {}: {err}", plugin.path))?;
 
-        // Write data to the child process's stdin
         if let Some(mut stdin) = child.stdin.take() {
-            let arguments = plugin.arguments;
-            let json_str = to_string(&arguments).unwrap();
-            stdin.write_all(json_str.as_bytes()).unwrap();
+            let arguments = to_string(&plugin.arguments).map_err(|err| err.to_string())?;
+            stdin
+                .write_all(arguments.as_bytes())
+                .map_err(|err| format!("Failed to write the arguments: {err}"))?;
         }
 
-        // Wait for the child process to finish and capture its stdout
-        match child.wait_with_output() {
-            Ok(output) => String::from_utf8(output.stdout).unwrap(),
-            Err(e) => e.to_string(),
+        // Read meanwhile, a plugin printing more than the pipe holds would never exit otherwise
+        let mut stdout = child.stdout.take().ok_or("No stdout")?;
+        let reader = thread::spawn(move || {
+            let mut output = String::new();
+            stdout.read_to_string(&mut output).map(|_| output)
+        });
+
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if start.elapsed() < timeout => thread::sleep(Duration::from_millis(10)),
+                Ok(None) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("Timed out after {} ms", timeout.as_millis()));
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        };
+
+        let output = reader
+            .join()
+            .map_err(|_| String::from("Failed to read the output"))?
+            .map_err(|err| format!("Failed to read the output: {err}"))?;
+        if !status.success() {
+            return Err(format!("Exited with {status}: {output}"));
         }
+
+        Ok(output)
+    }
+}
+
+/// Parses what a plugin printed, or logs why it is ignored: a plugin failing or printing
+/// something else than its result doesn't affect the others.
+fn parse_output<T: DeserializeOwned>(plugin_name: &str, output: &str) -> Option<T> {
+    from_str(output)
+        .map_err(|err| error!("Ignoring the output of plugin {plugin_name}: {err}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range, Url};
+
+    use std::fs;
+
+    use super::{parse_output, CustomResult, PluginManager, TypesNotification};
+
+    #[test]
+    fn test_plugin_directories() {
+        let temp_dir = std::env::temp_dir().join(format!("lever-plugins-{}", std::process::id()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(
+            temp_dir.join("lint.json"),
+            r#"[{"name": "lint", "path": "lint.py", "on": ["Save"], "arguments": [], "state": true}]"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.join("broken.json"), "[{").unwrap();
+        fs::write(temp_dir.join("README.md"), "").unwrap();
+
+        let mut manager = PluginManager::new();
+        manager.load_plugin_dirs(std::slice::from_ref(&temp_dir), None);
+        let paths: Vec<String> = manager
+            .plugins
+            .iter()
+            .map(|plugin| plugin.path.clone())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![temp_dir.join("lint.py").to_string_lossy().into_owned()]
+        );
+
+        // Loading again replaces the plugins of the directories
+        manager.load_plugin_dirs(&[temp_dir.clone(), temp_dir.join("missing")], None);
+        assert_eq!(manager.plugins.len(), 1);
+        manager.load_plugin_dirs(&[], None);
+        assert!(manager.plugins.is_empty());
+
+        fs::remove_dir_all(temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugins_without_local_workspace() {
+        let plugins = r#"[{"name": "lint", "path": "lint.py", "on": ["Save"], "arguments": [], "state": true}]"#;
+
+        let mut manager = PluginManager::new();
+        manager.load_plugins(Some(Url::parse("untitled:main.p4").unwrap()), plugins);
+        assert!(manager.plugins[0].arguments.is_empty());
+
+        manager.load_plugins(None, plugins);
+        assert!(manager.plugins[0].arguments.is_empty());
+    }
+
+    #[test]
+    fn test_plugin_diagnostics() {
+        let output = r#"{
+            "output_type": "Diagnostic",
+            "data": "[{\"range\": {\"start\": {\"line\": 1, \"character\": 0}, \"end\": {\"line\": 1, \"character\": 4}}, \"message\": \"Unused table\", \"severity\": 2}]"
+        }"#;
+
+        let result = parse_output::<CustomResult>("lint", output).unwrap();
+        assert!(result.output_type == TypesNotification::Diagnostic);
+        let diagnostics = parse_output::<Vec<Diagnostic>>("lint", &result.data).unwrap();
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                range: Range::new(Position::new(1, 0), Position::new(1, 4)),
+                severity: Some(DiagnosticSeverity::WARNING),
+                message: String::from("Unused table"),
+                ..Default::default()
+            }]
+        );
+
+        assert!(parse_output::<CustomResult>("lint", "Segmentation fault").is_none());
     }
 }
@@ -0,0 +1,105 @@
+use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+/// Edits toggling line comments on the lines of the range: the non-blank lines are commented out
+/// at their common indentation unless they all are comments already, in which case they are
+/// uncommented.
+pub fn toggle_line_comments(source_code: &str, range: Range, comment: &str) -> Vec<TextEdit> {
+    // A selection ending at the start of a line doesn't include it
+    let last_line = if range.end.character == 0 && range.end.line > range.start.line {
+        range.end.line - 1
+    } else {
+        range.end.line
+    };
+
+    let lines: Vec<(u32, &str)> = source_code
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i as u32, line))
+        .filter(|(i, line)| (range.start.line..=last_line).contains(i) && !line.trim().is_empty())
+        .collect();
+
+    let indentation = |line: &str| line.len() - line.trim_start().len();
+    let character = |line: &str, byte: usize| line[..byte].encode_utf16().count() as u32;
+
+    if lines
+        .iter()
+        .all(|(_, line)| line.trim_start().starts_with(comment))
+    {
+        return lines
+            .iter()
+            .map(|(i, line)| {
+                let start = indentation(line);
+                let mut end = start + comment.len();
+                if line[end..].starts_with(' ') {
+                    end += 1;
+                }
+                TextEdit::new(
+                    Range::new(
+                        Position::new(*i, character(line, start)),
+                        Position::new(*i, character(line, end)),
+                    ),
+                    String::new(),
+                )
+            })
+            .collect();
+    }
+
+    let Some(common_indentation) = lines.iter().map(|(_, line)| indentation(line)).min() else {
+        return vec![];
+    };
+    lines
+        .iter()
+        .map(|(i, line)| {
+            let position = Position::new(*i, character(line, common_indentation));
+            TextEdit::new(Range::new(position, position), format!("{comment} "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Position, Range, TextEdit};
+
+    use super::toggle_line_comments;
+
+    fn insert(line: u32, character: u32) -> TextEdit {
+        let position = Position::new(line, character);
+        TextEdit::new(Range::new(position, position), String::from("// "))
+    }
+
+    #[test]
+    fn test_toggle_line_comments() {
+        let source = "control C() {\n    apply {\n\n        drop();\n    }\n}\n";
+        let selection = Range::new(Position::new(1, 2), Position::new(4, 0));
+
+        assert_eq!(
+            toggle_line_comments(source, selection, "//"),
+            vec![insert(1, 4), insert(3, 4)]
+        );
+
+        let commented = "control C() {\n    // apply {\n\n    //     drop();\n    }\n}\n";
+        assert_eq!(
+            toggle_line_comments(commented, selection, "//"),
+            vec![
+                TextEdit::new(
+                    Range::new(Position::new(1, 4), Position::new(1, 7)),
+                    String::new()
+                ),
+                TextEdit::new(
+                    Range::new(Position::new(3, 4), Position::new(3, 7)),
+                    String::new()
+                ),
+            ]
+        );
+
+        let partly_commented = "    // apply {\n        drop();\n";
+        assert_eq!(
+            toggle_line_comments(
+                partly_commented,
+                Range::new(Position::new(0, 0), Position::new(1, 3)),
+                "//"
+            ),
+            vec![insert(0, 4), insert(1, 4)]
+        );
+    }
+}
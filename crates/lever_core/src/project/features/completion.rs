@@ -1,31 +1,306 @@
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    language_def::LanguageDefinition,
-    project::{AstQuery, SymbolTableQuery, Visitable},
+    language_def::{self, CompletionRule, Directive, LanguageDefinition},
+    project::{
+        metadata::{Symbol, SymbolId, Visibility},
+        AstQuery, NodeKind, SymbolTableQuery, Visitable,
+    },
 };
 use tower_lsp::lsp_types::{
     CompletionContext, CompletionItem, CompletionItemKind, CompletionItemLabelDetails,
-    CompletionTriggerKind, Position, Url,
+    CompletionList, CompletionTextEdit, CompletionTriggerKind, InsertTextFormat, Position, Range,
+    TextEdit, Url,
 };
 
+/// Completion lists are cut to this many items, the client asks again as the user types.
+const MAX_ITEMS: usize = 500;
+
+/// What a completion request asks for, depending on how it was triggered.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Trigger {
+    // `.` after a name, for its members
+    Member,
+    // A trigger character of a completion rule, like `<`, for names where the rule applies only
+    RuleCharacter(String),
+    // An explicit request, typing a name or another character, for the names in scope
+    Names,
+}
+
+pub fn get_trigger(
+    context: Option<&CompletionContext>,
+    is_rule_character: impl Fn(&str) -> bool,
+) -> Trigger {
+    let Some(context) = context else {
+        return Trigger::Names;
+    };
+    if context.trigger_kind != CompletionTriggerKind::TRIGGER_CHARACTER {
+        return Trigger::Names;
+    }
+
+    match context.trigger_character.as_deref().unwrap_or(".") {
+        "." => Trigger::Member,
+        character if is_rule_character(character) => Trigger::RuleCharacter(character.to_string()),
+        _ => Trigger::Names,
+    }
+}
+
+pub fn limit_list(items: Vec<CompletionItem>, prefix: &str) -> CompletionList {
+    limit(items, prefix, MAX_ITEMS)
+}
+
+/// Keeps the items starting with the prefix when there are more than `max_items`, marking the
+/// list as incomplete so that it's computed again as the prefix grows.
+fn limit(mut items: Vec<CompletionItem>, prefix: &str, max_items: usize) -> CompletionList {
+    if items.len() <= max_items {
+        return CompletionList {
+            is_incomplete: false,
+            items,
+        };
+    }
+
+    let prefix = prefix.to_lowercase();
+    items.retain(|item| item.label.to_lowercase().starts_with(&prefix));
+    items.truncate(max_items);
+
+    CompletionList {
+        is_incomplete: true,
+        items,
+    }
+}
+
 fn default_list(
     position: Position,
     query: &Arc<Mutex<impl SymbolTableQuery>>,
 ) -> Vec<CompletionItem> {
-    let mut items: Vec<CompletionItem> = Vec::new();
+    let query = query.lock().unwrap();
 
-    for symbol in query.lock().unwrap().get_symbols_at_pos(position) {
-        items.push(CompletionItem {
-            label: symbol.name.clone(),
-            kind: get_symbol_completion_type(&symbol.name),
-            ..Default::default()
-        });
+    query
+        .get_symbols_at_pos(position)
+        .into_iter()
+        .map(|symbol| {
+            // Types declared in other files aren't in this table, the kind is shown instead
+            let type_name = symbol
+                .type_symbol
+                .clone()
+                .filter(|type_id| type_id.file_id.is_none())
+                .and_then(|type_id| query.get_symbol(type_id))
+                .map(|type_symbol| type_symbol.name.clone());
+
+            CompletionItem {
+                kind: get_symbol_completion_type(&symbol.kind),
+                detail: Some(type_name.unwrap_or_else(|| symbol.kind.clone())),
+                label: symbol.name,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Adds the items suggested by plugins whose label isn't offered yet.
+pub fn merge_plugin_items(
+    mut items: Vec<CompletionItem>,
+    plugin_items: Vec<CompletionItem>,
+) -> Vec<CompletionItem> {
+    for item in plugin_items {
+        if !items.iter().any(|existing| existing.label == item.label) {
+            items.push(item);
+        }
     }
 
     items
 }
 
+/// Keywords and directives of the language, offered when the file has no symbols to complete,
+/// such as before it could be parsed.
+pub fn fallback_list(keywords: &[String], directives: &[Directive]) -> Vec<CompletionItem> {
+    let keyword_items = keywords.iter().map(|keyword| CompletionItem {
+        label: keyword.clone(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        ..Default::default()
+    });
+    let directive_items = directives.iter().map(|directive| CompletionItem {
+        label: directive.name.clone(),
+        kind: Some(CompletionItemKind::KEYWORD),
+        insert_text: directive.snippet.clone(),
+        insert_text_format: directive
+            .snippet
+            .as_ref()
+            .map(|_| InsertTextFormat::SNIPPET),
+        ..Default::default()
+    });
+
+    keyword_items.chain(directive_items).collect()
+}
+
+/// Completion rule applying at a position, with the names already used in the node of the rule.
+pub struct RuleContext {
+    pub rule: &'static CompletionRule,
+    pub used_names: Vec<String>,
+    // Symbol of the rule's target node, whose type has the fields to offer
+    pub target_symbol: Option<SymbolId>,
+}
+
+impl RuleContext {
+    fn allows(&self, name: &str, kind: Option<&str>) -> bool {
+        kind.is_none_or(|kind| self.rule.allows(kind))
+            && !(self.rule.unique && self.used_names.iter().any(|used| used == name))
+    }
+}
+
+/// Directives starting with what is written on the line before the position, as in `#inc`. Only
+/// offered when nothing else is written before it.
+pub fn directive_list(
+    directives: &[Directive],
+    line_prefix: &str,
+    position: Position,
+) -> Option<Vec<CompletionItem>> {
+    let written = line_prefix.trim_start();
+    if written.is_empty() || written.contains(char::is_whitespace) {
+        return None;
+    }
+
+    // The written text is replaced, clients don't consider characters like `#` part of a word
+    let range = Range::new(
+        Position::new(
+            position.line,
+            position
+                .character
+                .saturating_sub(written.encode_utf16().count() as u32),
+        ),
+        position,
+    );
+    let items: Vec<CompletionItem> = directives
+        .iter()
+        .filter(|directive| directive.name.starts_with(written))
+        .map(|directive| CompletionItem {
+            label: directive.name.clone(),
+            kind: Some(CompletionItemKind::KEYWORD),
+            insert_text_format: directive
+                .snippet
+                .as_ref()
+                .map(|_| InsertTextFormat::SNIPPET),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit::new(
+                range,
+                directive
+                    .snippet
+                    .clone()
+                    .unwrap_or_else(|| directive.name.clone()),
+            ))),
+            ..Default::default()
+        })
+        .collect();
+
+    (!items.is_empty()).then_some(items)
+}
+
+/// Completion rule of the innermost node around the position that has one.
+pub fn get_context_rule(
+    position: Position,
+    ast_query: &Arc<Mutex<impl AstQuery>>,
+) -> Option<RuleContext> {
+    let ast_query = ast_query.lock().unwrap();
+    let root_visit = ast_query.visit_root();
+
+    root_visit
+        .get_descendants()
+        .iter()
+        .rev() // Descendants are in pre-order, the innermost node comes last
+        .filter(|node| contains(node.get().range, position))
+        .find_map(|node| {
+            let NodeKind::Node(name) = &node.get().kind else {
+                return None;
+            };
+            let rule = LanguageDefinition::get()
+                .rule_with_name(name)?
+                .completion
+                .as_ref()?;
+
+            let field_name_node = rule
+                .fields
+                .as_ref()
+                .map(|fields| NodeKind::Node(fields.field_name_node.clone()));
+            // The name being written isn't considered used yet
+            let used_names = node
+                .get_descendants()
+                .iter()
+                .map(|descendant| descendant.get())
+                .filter(|descendant| {
+                    (descendant.symbol == language_def::Symbol::Usage
+                        || field_name_node.as_ref() == Some(&descendant.kind))
+                        && !contains(descendant.range, position)
+                })
+                .map(|descendant| descendant.content.clone())
+                .collect();
+
+            // In `hdr.ipv4`, the last member names the target
+            let target_symbol = rule.fields.as_ref().and_then(|fields| {
+                node.get_child_of_kind(NodeKind::Node(fields.target_node.clone()))?
+                    .get_descendants()
+                    .iter()
+                    .rev()
+                    .find_map(|descendant| descendant.get().linked_symbol.clone())
+            });
+
+            Some(RuleContext {
+                rule,
+                used_names,
+                target_symbol,
+            })
+        })
+}
+
+fn contains(range: Range, position: Position) -> bool {
+    position >= range.start && position <= range.end
+}
+
+fn context_list(symbols: Vec<Symbol>, context: &RuleContext) -> Vec<CompletionItem> {
+    let rule = context.rule;
+    let extra_items = rule.extra_items.iter().map(|name| (name.clone(), None));
+    let symbol_items = symbols
+        .into_iter()
+        .map(|symbol| (symbol.name, Some(symbol.kind)));
+
+    symbol_items
+        .chain(extra_items)
+        .filter(|(name, kind)| context.allows(name, kind.as_deref()))
+        .map(|(name, kind)| CompletionItem {
+            insert_text: rule
+                .insert_suffix
+                .as_ref()
+                .map(|suffix| format!("{name}{suffix}")),
+            label: name,
+            kind: rule.completion_type.as_ref().map_or_else(
+                || get_symbol_completion_type(kind.as_deref()?),
+                |completion_type| Some(completion_type.get()),
+            ),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Lists the fields not assigned yet, with the name of their type as detail.
+pub fn field_list(
+    mut fields: Vec<(Symbol, Option<String>)>,
+    context: &RuleContext,
+) -> Vec<CompletionItem> {
+    fields.retain(|(field, _)| !context.used_names.contains(&field.name));
+    member_list(fields)
+}
+
+/// Lists the fields accessible after a `.`, with the name of their type as detail.
+pub fn member_list(fields: Vec<(Symbol, Option<String>)>) -> Vec<CompletionItem> {
+    fields
+        .into_iter()
+        .map(|(field, type_name)| CompletionItem {
+            label: field.name,
+            kind: Some(CompletionItemKind::FIELD),
+            detail: type_name,
+            ..Default::default()
+        })
+        .collect()
+}
+
 fn get_symbol_completion_type(symbol_kind: &str) -> Option<CompletionItemKind> {
     Some(
         LanguageDefinition::get()
@@ -40,6 +315,7 @@ fn get_symbol_completion_type(symbol_kind: &str) -> Option<CompletionItemKind> {
 pub fn get_imported_list(
     uri: &Url,
     st_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    context: Option<&RuleContext>,
 ) -> Vec<CompletionItem> {
     let query = st_query.lock().unwrap();
 
@@ -47,10 +323,21 @@ pub fn get_imported_list(
         .get_symbols_at_root()
         .0
         .iter()
+        .filter(|s| s.visibility == Visibility::Exported)
+        .filter(|s| context.is_none_or(|context| context.allows(&s.name, Some(&s.kind))))
         .map(|s| CompletionItem {
+            insert_text: context
+                .and_then(|context| context.rule.insert_suffix.as_ref())
+                .map(|suffix| format!("{}{suffix}", s.name)),
             label: s.name.clone(),
             label_details: Some(CompletionItemLabelDetails {
-                detail: Some(uri.path_segments().unwrap().last().unwrap().to_string()),
+                detail: Some(
+                    uri.path_segments()
+                        .unwrap()
+                        .next_back()
+                        .unwrap()
+                        .to_string(),
+                ),
                 description: None,
             }),
             kind: get_symbol_completion_type(&s.kind),
@@ -65,8 +352,20 @@ pub fn get_list(
     symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
     context: Option<CompletionContext>,
 ) -> Option<Vec<CompletionItem>> {
-    if let Some(context) = context {
-        if context.trigger_kind == CompletionTriggerKind::TRIGGER_CHARACTER {
+    let trigger = get_trigger(context.as_ref(), |character| {
+        LanguageDefinition::get().is_rule_trigger_character(character)
+    });
+    match trigger {
+        Trigger::RuleCharacter(character) => {
+            let context = get_context_rule(position, ast_query)
+                .filter(|context| context.rule.is_triggered_by(&character))?;
+            let symbols = symbol_table_query
+                .lock()
+                .unwrap()
+                .get_symbols_at_pos(position);
+            return Some(context_list(symbols, &context));
+        }
+        Trigger::Member => {
             let ast_query = ast_query.lock().unwrap();
             let root_visit = ast_query.visit_root();
 
@@ -99,7 +398,456 @@ pub fn get_list(
                 }
             }
         }
+        Trigger::Names => {}
+    }
+
+    if let Some(context) = get_context_rule(position, ast_query) {
+        let symbols = symbol_table_query
+            .lock()
+            .unwrap()
+            .get_symbols_at_pos(position);
+        return Some(context_list(symbols, &context));
     }
 
     Some(default_list(position, symbol_table_query))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use indextree::Arena;
+    use tower_lsp::lsp_types::{
+        CompletionContext, CompletionItem, CompletionItemKind, CompletionTextEdit,
+        CompletionTriggerKind, InsertTextFormat, Position, Range, TextEdit,
+    };
+
+    use super::{
+        context_list, default_list, directive_list, fallback_list, field_list, get_context_rule,
+        get_list, get_trigger, limit, merge_plugin_items, Trigger,
+    };
+    use crate::language_def::{
+        self, CompletionRule, Directive, FieldCompletion, Symbol as SymbolRule,
+    };
+    use crate::lsp_mappings::SymbolCompletionType;
+    use crate::project::metadata::test_utils::{init, node, range, rule_context, symbol, test_url};
+    use crate::project::metadata::{Ast, AstManager, SymbolTableManager};
+
+    #[test]
+    fn test_symbol_kinds_and_types() {
+        language_def::load_test_definition();
+
+        // control C() {}
+        // C() c;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 10)));
+        let control = arena.new_node(node("Control", "", init("Control", None), range(0, 0, 10)));
+        control.append(
+            arena.new_node(node("Name", "C", SymbolRule::None, range(0, 0, 10))),
+            &mut arena,
+        );
+        root.append(control, &mut arena);
+        let instance = arena.new_node(node(
+            "Instance",
+            "",
+            init("Instance", Some("Type")),
+            range(1, 0, 10),
+        ));
+        instance.append(
+            arena.new_node(node("Type", "C", SymbolRule::Usage, range(1, 0, 10))),
+            &mut arena,
+        );
+        instance.append(
+            arena.new_node(node("Name", "c", SymbolRule::None, range(1, 0, 10))),
+            &mut arena,
+        );
+        root.append(instance, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let query = Arc::new(Mutex::new(SymbolTableManager::new(&mut ast, test_url())));
+
+        let mut items: Vec<(String, Option<CompletionItemKind>, Option<String>)> =
+            default_list(Position::new(2, 0), &query)
+                .into_iter()
+                .map(|item| (item.label, item.kind, item.detail))
+                .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            items,
+            vec![
+                (
+                    String::from("C"),
+                    Some(CompletionItemKind::CLASS),
+                    Some(String::from("Control"))
+                ),
+                (
+                    String::from("c"),
+                    Some(CompletionItemKind::VARIABLE),
+                    Some(String::from("C"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugin_items_without_duplicates() {
+        let item = |label: &str, detail: &str| CompletionItem {
+            label: label.to_string(),
+            detail: Some(detail.to_string()),
+            ..Default::default()
+        };
+
+        let items = merge_plugin_items(
+            vec![item("mark_to_drop", "Action")],
+            vec![item("mark_to_drop", "v1model"), item("hash", "v1model")],
+        );
+        assert_eq!(
+            items,
+            vec![item("mark_to_drop", "Action"), item("hash", "v1model")]
+        );
+    }
+
+    #[test]
+    fn test_completion_triggers() {
+        let trigger = |trigger_kind, trigger_character: Option<&str>| {
+            let context = CompletionContext {
+                trigger_kind,
+                trigger_character: trigger_character.map(String::from),
+            };
+            get_trigger(Some(&context), |character| character == "<")
+        };
+
+        assert_eq!(
+            trigger(CompletionTriggerKind::TRIGGER_CHARACTER, Some(".")),
+            Trigger::Member
+        );
+        assert_eq!(
+            trigger(CompletionTriggerKind::TRIGGER_CHARACTER, Some("<")),
+            Trigger::RuleCharacter(String::from("<"))
+        );
+        assert_eq!(
+            trigger(CompletionTriggerKind::TRIGGER_CHARACTER, Some("$")),
+            Trigger::Names
+        );
+        assert_eq!(
+            trigger(CompletionTriggerKind::INVOKED, None),
+            Trigger::Names
+        );
+        assert_eq!(
+            trigger(
+                CompletionTriggerKind::TRIGGER_FOR_INCOMPLETE_COMPLETIONS,
+                None
+            ),
+            Trigger::Names
+        );
+        assert_eq!(get_trigger(None, |_| true), Trigger::Names);
+    }
+
+    #[test]
+    fn test_transition_target_completion() {
+        let symbols = vec![
+            symbol("ethernet_t", "Header", Range::default()),
+            symbol("start", "State", Range::default()),
+            symbol("parse_ipv4", "State", Range::default()),
+        ];
+        let transition = rule_context(
+            CompletionRule {
+                symbol_kinds: vec![String::from("State")],
+                extra_items: vec![String::from("accept"), String::from("reject")],
+                completion_type: Some(SymbolCompletionType::Value),
+                ..Default::default()
+            },
+            &[],
+        );
+
+        let items = context_list(symbols, &transition);
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["start", "parse_ipv4", "accept", "reject"]
+        );
+        assert!(
+            items
+                .iter()
+                .all(|item| item.kind == Some(CompletionItemKind::VALUE)
+                    && item.insert_text.is_none())
+        );
+    }
+
+    #[test]
+    fn test_table_actions_completion() {
+        let action = |name: &str| symbol(name, "Action", Range::default());
+        let actions = rule_context(
+            CompletionRule {
+                symbol_kinds: vec![String::from("Action")],
+                extra_items: vec![String::from("NoAction")],
+                completion_type: Some(SymbolCompletionType::Function),
+                insert_suffix: Some(String::from(";")),
+                unique: true,
+                ..Default::default()
+            },
+            &["drop"],
+        );
+
+        let items = context_list(vec![action("forward"), action("drop")], &actions);
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.insert_text.as_deref())
+                .collect::<Vec<Option<&str>>>(),
+            vec![Some("forward;"), Some("NoAction;")]
+        );
+    }
+
+    #[test]
+    fn test_directive_completion() {
+        let directives = vec![
+            Directive {
+                name: String::from("#include"),
+                snippet: Some(String::from("#include \"$0\"")),
+            },
+            Directive {
+                name: String::from("#define"),
+                snippet: None,
+            },
+        ];
+
+        let items = directive_list(&directives, "#inc", Position::new(2, 4)).unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "#include");
+        assert_eq!(items[0].insert_text_format, Some(InsertTextFormat::SNIPPET));
+        assert_eq!(
+            items[0].text_edit,
+            Some(CompletionTextEdit::Edit(TextEdit::new(
+                Range::new(Position::new(2, 0), Position::new(2, 4)),
+                String::from("#include \"$0\"")
+            )))
+        );
+        assert!(directive_list(&directives, "x = #inc", Position::new(2, 8)).is_none());
+    }
+
+    #[test]
+    fn test_fallback_offers_keywords() {
+        // A file containing only `}}}` has no symbols
+        let keywords = vec![String::from("control"), String::from("action")];
+        let directives = vec![Directive {
+            name: String::from("#include"),
+            snippet: Some(String::from("#include \"$0\"")),
+        }];
+
+        let items = fallback_list(&keywords, &directives);
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["control", "action", "#include"]
+        );
+        assert!(items
+            .iter()
+            .all(|item| item.kind == Some(CompletionItemKind::KEYWORD)));
+        assert_eq!(items[2].insert_text_format, Some(InsertTextFormat::SNIPPET));
+    }
+
+    #[test]
+    fn test_select_key_completion() {
+        let symbol = |name: &str, kind: &str| symbol(name, kind, Range::default());
+        // transition select(|) { ... }
+        let select = rule_context(
+            CompletionRule {
+                symbol_kinds: vec![String::from("Variable"), String::from("Parameter")],
+                completion_type: Some(SymbolCompletionType::Variable),
+                ..Default::default()
+            },
+            &[],
+        );
+
+        let items = context_list(
+            vec![
+                symbol("headers_t", "Header"),
+                symbol("drop", "Action"),
+                symbol("hdr", "Parameter"),
+                symbol("next_type", "Variable"),
+            ],
+            &select,
+        );
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["hdr", "next_type"]
+        );
+    }
+
+    #[test]
+    fn test_select_key_completion_in_ast() {
+        language_def::load_test_definition();
+
+        // control C(T hdr) {
+        //     T next_type;
+        //     action drop() {}
+        //     select(|)
+        // }
+        let lines = Range::new(Position::new(0, 0), Position::new(4, 1));
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, lines));
+        let control = arena.new_node(node("Control", "", init("Control", None), lines));
+        control.append(
+            arena.new_node(node("Name", "C", SymbolRule::None, range(0, 8, 9))),
+            &mut arena,
+        );
+        root.append(control, &mut arena);
+        let declarations = [
+            ("Parameter", "hdr", Some("Type"), range(0, 10, 15)),
+            ("Instance", "next_type", Some("Type"), range(1, 4, 16)),
+            ("Action", "drop", None, range(2, 4, 20)),
+        ];
+        for (kind, name, type_node, range) in declarations {
+            let declaration = arena.new_node(node(kind, "", init(kind, type_node), range));
+            control.append(declaration, &mut arena);
+            if let Some(type_node) = type_node {
+                declaration.append(
+                    arena.new_node(node(type_node, "T", SymbolRule::Usage, range)),
+                    &mut arena,
+                );
+            }
+            declaration.append(
+                arena.new_node(node("Name", name, SymbolRule::None, range)),
+                &mut arena,
+            );
+        }
+        control.append(
+            arena.new_node(node(
+                "Select",
+                "select()",
+                SymbolRule::None,
+                range(3, 4, 13),
+            )),
+            &mut arena,
+        );
+
+        let mut ast = Ast::initialize(arena, root);
+        let symbol_table = Arc::new(Mutex::new(SymbolTableManager::new(&mut ast, test_url())));
+        let ast = Arc::new(Mutex::new(AstManager { ast }));
+        let position = Position::new(3, 11);
+
+        let context = get_context_rule(position, &ast).unwrap();
+        assert_eq!(context.rule.symbol_kinds, vec!["Parameter", "Instance"]);
+        assert!(get_context_rule(Position::new(1, 8), &ast).is_none());
+
+        // Only the variables are offered, not the control nor the action
+        let mut labels: Vec<String> = get_list(position, &ast, &symbol_table, None)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+        labels.sort();
+        assert_eq!(labels, vec!["hdr", "next_type"]);
+    }
+
+    #[test]
+    fn test_type_argument_completion() {
+        let symbol = |name: &str, kind: &str| symbol(name, kind, Range::default());
+        // `Register<` only offers types
+        let type_argument = rule_context(
+            CompletionRule {
+                symbol_kinds: vec![String::from("Type"), String::from("Header")],
+                extra_items: vec![String::from("bit"), String::from("bool")],
+                completion_type: Some(SymbolCompletionType::TypeParameter),
+                trigger_characters: vec![String::from("<")],
+                ..Default::default()
+            },
+            &[],
+        );
+
+        assert!(type_argument.rule.is_triggered_by("<"));
+        assert!(!type_argument.rule.is_triggered_by(">"));
+
+        let items = context_list(
+            vec![
+                symbol("counter_t", "Type"),
+                symbol("ipv4_t", "Header"),
+                symbol("ingress", "Control"),
+            ],
+            &type_argument,
+        );
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["counter_t", "ipv4_t", "bit", "bool"]
+        );
+    }
+
+    #[test]
+    fn test_limited_list_is_incomplete() {
+        let items: Vec<CompletionItem> = (0..10)
+            .flat_map(|i| [format!("reg_{i}"), format!("meta_{i}")])
+            .map(|label| CompletionItem {
+                label,
+                ..Default::default()
+            })
+            .collect();
+
+        let complete = limit(items.clone(), "", 20);
+        assert!(!complete.is_incomplete);
+        assert_eq!(complete.items.len(), 20);
+
+        let truncated = limit(items, "Me", 5);
+        assert!(truncated.is_incomplete);
+        assert_eq!(
+            truncated
+                .items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["meta_0", "meta_1", "meta_2", "meta_3", "meta_4"]
+        );
+    }
+
+    #[test]
+    fn test_initializer_field_completion() {
+        let field = |name: &str| symbol(name, "Field", Range::default());
+        // `ipv4_t h = { version = 4, | }`
+        let initializer = rule_context(
+            CompletionRule {
+                fields: Some(FieldCompletion {
+                    target_node: String::from("Type"),
+                    field_name_node: String::from("FieldName"),
+                }),
+                ..Default::default()
+            },
+            &["version"],
+        );
+
+        let items = field_list(
+            vec![
+                (field("version"), Some(String::from("bit<4>"))),
+                (field("ttl"), Some(String::from("bit<8>"))),
+                (field("srcAddr"), None),
+            ],
+            &initializer,
+        );
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| (item.label.as_str(), item.detail.as_deref()))
+                .collect::<Vec<(&str, Option<&str>)>>(),
+            vec![("ttl", Some("bit<8>")), ("srcAddr", None)]
+        );
+        assert!(items
+            .iter()
+            .all(|item| item.kind == Some(CompletionItemKind::FIELD)));
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::{Arc, Mutex};
+
+use crate::language_def::{CatchAllCheck, LanguageDefinition};
+use crate::project::{AstQuery, NodeKind, SymbolTableQuery, VisitNode, Visitable};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use super::provider::DiagnosticProvider;
+
+pub struct MissingCatchAll {}
+
+impl DiagnosticProvider for MissingCatchAll {
+    fn get_diagnostics(
+        _uri: &Url,
+        ast_query: &Arc<Mutex<impl AstQuery>>,
+        _symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    ) -> Vec<Diagnostic> {
+        let checks = &LanguageDefinition::get().catch_all_checks;
+        if checks.is_empty() {
+            return vec![];
+        }
+
+        let ast_query = ast_query.lock().unwrap();
+        find_missing_catch_alls(&ast_query.visit_root(), checks)
+    }
+}
+
+/// Reports the checked expressions none of whose cases matches anything.
+fn find_missing_catch_alls(root: &VisitNode, checks: &[CatchAllCheck]) -> Vec<Diagnostic> {
+    root.get_descendants()
+        .iter()
+        .filter_map(|node| {
+            let check = checks
+                .iter()
+                .find(|check| node.get().kind == NodeKind::Node(check.expression_node.clone()))?;
+
+            let has_catch_all = node
+                .get_descendants()
+                .into_iter()
+                .filter(|descendant| {
+                    descendant.get().kind == NodeKind::Node(check.case_node.clone())
+                })
+                .any(|case| {
+                    case.get_child_of_kind(NodeKind::Node(check.key_node.clone()))
+                        .is_some_and(|key| {
+                            check
+                                .catch_all_keys
+                                .contains(&key.get().content.trim().to_string())
+                        })
+                });
+            if has_catch_all {
+                return None;
+            }
+
+            let keys: Vec<String> = check
+                .catch_all_keys
+                .iter()
+                .map(|key| format!("`{key}`"))
+                .collect();
+            Some(Diagnostic::new(
+                node.get().range,
+                Some(DiagnosticSeverity::WARNING),
+                Some(NumberOrString::String("catch-all".to_string())),
+                Some("Catch-all check".to_string()),
+                format!("No catch-all case ({}).", keys.join(" or ")),
+                None,
+                None,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_missing_catch_alls;
+    use crate::language_def::{CatchAllCheck, Symbol as SymbolRule};
+    use crate::project::metadata::test_utils::{node, range};
+    use crate::project::VisitNode;
+    use indextree::Arena;
+
+    #[test]
+    fn test_select_without_default() {
+        let check = CatchAllCheck {
+            expression_node: String::from("Select"),
+            case_node: String::from("Case"),
+            key_node: String::from("Key"),
+            catch_all_keys: vec![String::from("default"), String::from("_")],
+        };
+
+        // select (t) { 1: accept; <last_key>: reject; }
+        let select = |last_key: &str| {
+            let mut arena = Arena::new();
+            let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 40)));
+            let select = arena.new_node(node("Select", "", SymbolRule::None, range(0, 0, 40)));
+            root.append(select, &mut arena);
+            for (key, start) in [("1", 13), (last_key, 24)] {
+                let case = arena.new_node(node(
+                    "Case",
+                    "",
+                    SymbolRule::None,
+                    range(0, start, start + 10),
+                ));
+                case.append(
+                    arena.new_node(node(
+                        "Key",
+                        key,
+                        SymbolRule::None,
+                        range(0, start, start + 1),
+                    )),
+                    &mut arena,
+                );
+                select.append(case, &mut arena);
+            }
+            find_missing_catch_alls(&VisitNode::new(&arena, root), std::slice::from_ref(&check))
+        };
+
+        let diagnostics = select("2");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "No catch-all case (`default` or `_`)."
+        );
+
+        assert!(select("default").is_empty());
+        assert!(select("_").is_empty());
+    }
+}
@@ -8,7 +8,9 @@ use std::{
 };
 
 use crate::project::{AstQuery, SymbolTableQuery};
-use tower_lsp::lsp_types::{Diagnostic, Url};
+use tower_lsp::lsp_types::{
+    Diagnostic, DiagnosticSeverity, DiagnosticTag, NumberOrString, Range, Url,
+};
 
 use super::provider::DiagnosticProvider;
 
@@ -27,6 +29,10 @@ impl ImportErrors {
         entry.push(diag);
     }
 
+    pub fn add_unresolved_include(uri: Url, range: Range, file_name: &str) {
+        ImportErrors::add_error(uri, unresolved_include(range, file_name));
+    }
+
     pub fn clear(uri: &Url) {
         let mut lock = BUFFER_INSTANCE.lock().unwrap();
         if let Some(diags) = lock.get_mut().get_mut(uri) {
@@ -35,6 +41,27 @@ impl ImportErrors {
     }
 }
 
+fn unresolved_include(range: Range, file_name: &str) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::ERROR),
+        message: format!("cannot open included file '{file_name}'"),
+        ..Default::default()
+    }
+}
+
+/// Hint on an include none of whose symbols the including file uses.
+pub fn unused_include(range: Range, file_name: &str) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(DiagnosticSeverity::HINT),
+        code: Some(NumberOrString::String(String::from("unused-include"))),
+        message: format!("'{file_name}' is included but none of its symbols are used"),
+        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+        ..Default::default()
+    }
+}
+
 impl DiagnosticProvider for ImportErrors {
     fn get_diagnostics(
         uri: &Url,
@@ -51,3 +78,23 @@ impl DiagnosticProvider for ImportErrors {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tower_lsp::lsp_types::{DiagnosticSeverity, Range};
+
+    use super::unresolved_include;
+    use crate::utils::resolve_include;
+
+    #[test]
+    fn test_bogus_include() {
+        let roots = vec![PathBuf::from("/lever/bogus/include/path")];
+        assert!(resolve_include(&roots, "x.p4").is_none());
+
+        let diagnostic = unresolved_include(Range::default(), "x.p4");
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostic.message, "cannot open included file 'x.p4'");
+    }
+}
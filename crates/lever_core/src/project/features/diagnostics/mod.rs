@@ -1,6 +1,14 @@
+mod catch_all;
 mod import_errors;
+mod naming;
 mod parse;
 mod provider;
+mod readonly_writes;
+mod translation;
+mod undefined;
+mod width_overflow;
 
-pub use import_errors::ImportErrors;
+pub use import_errors::{unused_include, ImportErrors};
+pub use naming::{check_naming_conventions, compile_naming_conventions, NamingConvention};
 pub use provider::{get_full, get_quick};
+pub use undefined::get_undefined_usages;
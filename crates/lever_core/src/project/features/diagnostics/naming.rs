@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+
+use crate::project::metadata::Symbol;
+
+/// Pattern the names of the symbols of a kind must entirely match.
+pub struct NamingConvention {
+    pattern: String,
+    regex: Regex,
+}
+
+/// Compiles the pattern of each kind once, when the settings change. Invalid patterns are
+/// skipped.
+pub fn compile_naming_conventions(
+    conventions: &BTreeMap<String, String>,
+) -> BTreeMap<String, NamingConvention> {
+    conventions
+        .iter()
+        .filter_map(
+            |(kind, pattern)| match Regex::new(&format!("^(?:{pattern})$")) {
+                Ok(regex) => Some((
+                    kind.clone(),
+                    NamingConvention {
+                        pattern: pattern.clone(),
+                        regex,
+                    },
+                )),
+                Err(err) => {
+                    warn!("Invalid naming convention for {kind}: {err}");
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Warns about the symbols whose name doesn't match the convention of their kind.
+pub fn check_naming_conventions(
+    symbols: &[Symbol],
+    conventions: &BTreeMap<String, NamingConvention>,
+) -> Vec<Diagnostic> {
+    symbols
+        .iter()
+        .filter_map(|symbol| {
+            let convention = conventions.get(&symbol.kind)?;
+            if convention.regex.is_match(&symbol.name) {
+                return None;
+            }
+
+            Some(Diagnostic::new(
+                symbol.def_range,
+                Some(DiagnosticSeverity::WARNING),
+                Some(NumberOrString::String("naming".to_string())),
+                Some("Naming convention".to_string()),
+                format!(
+                    "{} `{}` doesn't match `{}`.",
+                    symbol.kind, symbol.name, convention.pattern
+                ),
+                None,
+                None,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tower_lsp::lsp_types::Range;
+
+    use super::{check_naming_conventions, compile_naming_conventions};
+    use crate::project::metadata::test_utils;
+
+    #[test]
+    fn test_action_not_in_snake_case() {
+        let symbol = |name, kind| test_utils::symbol(name, kind, Range::default());
+        let symbols = vec![
+            symbol("DropPacket", "Action"),
+            symbol("drop_packet", "Action"),
+            symbol("headers_t", "Type"),
+        ];
+        let conventions = compile_naming_conventions(&BTreeMap::from([
+            (String::from("Action"), String::from("[a-z][a-z0-9_]*")),
+            (String::from("Type"), String::from("[a-z")),
+        ]));
+
+        let diagnostics = check_naming_conventions(&symbols, &conventions);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "Action `DropPacket` doesn't match `[a-z][a-z0-9_]*`."
+        );
+    }
+}
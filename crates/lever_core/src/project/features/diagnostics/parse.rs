@@ -1,45 +1,68 @@
-use std::sync::{Arc, Mutex};
-
-use crate::project::{AstQuery, NodeKind, SymbolTableQuery, VisitNode, Visitable};
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Url};
-
-use super::provider::DiagnosticProvider;
-
-pub struct Parse {}
-
-impl DiagnosticProvider for Parse {
-    fn get_diagnostics(
-        _uri: &Url,
-        ast_query: &Arc<Mutex<impl AstQuery>>,
-        _symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
-    ) -> Vec<Diagnostic> {
-        let ast_query = ast_query.lock().unwrap();
-        let root = ast_query.visit_root();
-        let mut errors: Vec<(VisitNode, Option<String>)> = vec![];
-        for node in root.get_descendants() {
-            if let NodeKind::Error(msg) = &node.get().kind {
-                errors.push((node, msg.clone()));
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString};
+use tree_sitter::Tree;
+
+use crate::utils;
+
+// A badly broken file would otherwise flood the client
+const MAX_SYNTAX_ERRORS: usize = 100;
+
+/// Reports the `ERROR` and `MISSING` nodes of the syntax tree, in source order.
+pub fn get_syntax_errors(tree: &Tree, source_code: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    let mut cursor = tree.walk();
+
+    loop {
+        let node = cursor.node();
+        if node.is_error() || node.is_missing() {
+            let message = if node.is_missing() {
+                format!("Missing {}", node.kind())
+            } else {
+                error_message(&utils::get_node_text(&node, source_code))
             };
+            diagnostics.push(Diagnostic::new(
+                utils::ts_range_to_lsp_range(node.range()),
+                Some(DiagnosticSeverity::ERROR),
+                Some(NumberOrString::String("parsing".to_string())),
+                Some("AST".to_string()),
+                message,
+                None,
+                None,
+            ));
+            if diagnostics.len() == MAX_SYNTAX_ERRORS {
+                return diagnostics;
+            }
+        } else if node.has_error() && cursor.goto_first_child() {
+            // Only subtrees containing an error are worth walking
+            continue;
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return diagnostics;
+            }
         }
+    }
+}
+
+// Errors can cover whole blocks, only their first line is quoted
+fn error_message(text: &str) -> String {
+    match text.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => format!("Syntax error near '{line}'"),
+        None => "Syntax error".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::error_message;
 
-        errors
-            .into_iter()
-            .map(|(node, msg)| {
-                Diagnostic::new(
-                    node.get().range,
-                    Some(DiagnosticSeverity::ERROR),
-                    Some(tower_lsp::lsp_types::NumberOrString::String(
-                        "parsing".to_string(),
-                    )),
-                    Some("AST".to_string()),
-                    msg.map_or_else(
-                        || "Syntax error.".to_string(),
-                        |msg| format!("Syntax error: {msg}"),
-                    ),
-                    None,
-                    None,
-                )
-            })
-            .collect()
+    #[test]
+    fn test_syntax_error_message() {
+        assert_eq!(error_message("x = ;"), "Syntax error near 'x = ;'");
+        assert_eq!(
+            error_message("\n    apply {\n        drop()\n    }"),
+            "Syntax error near 'apply {'"
+        );
+        assert_eq!(error_message(""), "Syntax error");
     }
 }
@@ -1,9 +1,15 @@
 use std::sync::{Arc, Mutex};
 
-use tower_lsp::lsp_types::{Diagnostic, Url};
+use tower_lsp::lsp_types::{Diagnostic, Range, Url};
+use tree_sitter::Tree;
 
-use super::parse::Parse;
+use super::catch_all::MissingCatchAll;
+use super::parse;
 
+use super::readonly_writes::ReadonlyWrites;
+use super::translation::Translation;
+use super::undefined::Undefined;
+use super::width_overflow::WidthOverflow;
 use crate::project::{features::diagnostics::ImportErrors, AstQuery, SymbolTableQuery};
 
 macro_rules! diags {
@@ -29,23 +35,122 @@ pub trait DiagnosticProvider {
 }
 
 pub fn get_quick(
+    uri: &Url,
+    tree: &Tree,
+    source_code: &str,
+    ast_query: &Arc<Mutex<impl AstQuery>>,
+    symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+) -> Vec<Diagnostic> {
+    with_syntax_errors(
+        parse::get_syntax_errors(tree, source_code),
+        uri,
+        ast_query,
+        symbol_table_query,
+    )
+}
+
+fn with_syntax_errors(
+    mut syntax_errors: Vec<Diagnostic>,
     uri: &Url,
     ast_query: &Arc<Mutex<impl AstQuery>>,
     symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
 ) -> Vec<Diagnostic> {
     diags![
-        Parse::get_diagnostics(uri, ast_query, symbol_table_query),
+        syntax_errors,
         ImportErrors::get_diagnostics(uri, ast_query, symbol_table_query)
     ]
 }
 
 pub fn get_full(
     uri: &Url,
+    tree: &Tree,
+    source_code: &str,
     ast_query: &Arc<Mutex<impl AstQuery>>,
     symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
 ) -> Vec<Diagnostic> {
-    diags![
-        Parse::get_diagnostics(uri, ast_query, symbol_table_query),
-        ImportErrors::get_diagnostics(uri, ast_query, symbol_table_query)
-    ]
+    let semantic = diags![
+        Translation::get_diagnostics(uri, ast_query, symbol_table_query),
+        Undefined::get_diagnostics(uri, ast_query, symbol_table_query),
+        ReadonlyWrites::get_diagnostics(uri, ast_query, symbol_table_query),
+        WidthOverflow::get_diagnostics(uri, ast_query, symbol_table_query),
+        MissingCatchAll::get_diagnostics(uri, ast_query, symbol_table_query)
+    ];
+
+    let syntax_errors = parse::get_syntax_errors(tree, source_code);
+    let syntax_ranges: Vec<Range> = syntax_errors.iter().map(|diag| diag.range).collect();
+
+    merge(
+        &syntax_ranges,
+        with_syntax_errors(syntax_errors, uri, ast_query, symbol_table_query),
+        semantic,
+    )
+}
+
+/// Merges the quick diagnostics with the semantic ones, dropping duplicates and the semantic
+/// diagnostics overlapping one of the `syntax_errors`, since the semantics are unreliable there.
+fn merge(
+    syntax_errors: &[Range],
+    quick: Vec<Diagnostic>,
+    semantic: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    let mut diags: Vec<Diagnostic> = vec![];
+    for diag in quick.into_iter().chain(semantic.into_iter().filter(|diag| {
+        !syntax_errors
+            .iter()
+            .any(|range| overlaps(*range, diag.range))
+    })) {
+        if !diags.contains(&diag) {
+            diags.push(diag);
+        }
+    }
+
+    diags
+}
+
+// Ranges only touching each other don't overlap
+fn overlaps(a: Range, b: Range) -> bool {
+    a.start < b.end && b.start < a.end
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
+
+    use super::merge;
+
+    fn error(line: u32, start: u32, end: u32, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, start), Position::new(line, end)),
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: message.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_syntax_error_supersedes_semantics() {
+        // `#include "missing.p4"`, `bit<8> x = y +;` then `z = w;`. Diagnostics only touching the
+        // syntax error are kept
+        let unresolved_include = error(0, 9, 21, "Unresolved include.");
+        let syntax_error = error(1, 11, 15, "Syntax error.");
+        let quick = vec![unresolved_include.clone(), syntax_error.clone()];
+        let semantic = vec![
+            error(0, 9, 21, "Undefined symbol `missing`."),
+            error(1, 11, 12, "Undefined symbol `y`."),
+            error(1, 15, 16, "Width of `x` overflows."),
+            error(2, 4, 5, "Undefined symbol `w`."),
+            error(2, 4, 5, "Undefined symbol `w`."),
+        ];
+
+        assert_eq!(
+            merge(&[syntax_error.range], quick, semantic),
+            vec![
+                unresolved_include,
+                syntax_error,
+                error(0, 9, 21, "Undefined symbol `missing`."),
+                error(1, 15, 16, "Width of `x` overflows."),
+                error(2, 4, 5, "Undefined symbol `w`."),
+            ]
+        );
+    }
 }
@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+
+use crate::language_def::LanguageDefinition;
+use crate::project::metadata::{Symbol, SymbolId};
+use crate::project::{AstQuery, NodeKind, SymbolTableQuery, VisitNode, Visitable};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use super::provider::DiagnosticProvider;
+
+pub struct ReadonlyWrites {}
+
+impl DiagnosticProvider for ReadonlyWrites {
+    fn get_diagnostics(
+        _uri: &Url,
+        ast_query: &Arc<Mutex<impl AstQuery>>,
+        symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    ) -> Vec<Diagnostic> {
+        let write_nodes: Vec<NodeKind> = LanguageDefinition::get()
+            .write_nodes
+            .iter()
+            .map(|name| NodeKind::Node(name.clone()))
+            .collect();
+        if write_nodes.is_empty() {
+            return vec![];
+        }
+
+        let ast_query = ast_query.lock().unwrap();
+        let symbol_table_query = symbol_table_query.lock().unwrap();
+
+        find_readonly_writes(&ast_query.visit_root(), &write_nodes, |symbol_id| {
+            // Symbols of other files aren't known here
+            if symbol_id.get_file_id().is_some() {
+                return None;
+            }
+            symbol_table_query.get_symbol(symbol_id.clone()).cloned()
+        })
+    }
+}
+
+/// Reports the write nodes whose written symbol is read-only. In `hdr.ipv4.ttl`, the written
+/// symbol is `hdr`, the first one linked.
+fn find_readonly_writes(
+    root: &VisitNode,
+    write_nodes: &[NodeKind],
+    get_symbol: impl Fn(&SymbolId) -> Option<Symbol>,
+) -> Vec<Diagnostic> {
+    root.get_descendants()
+        .iter()
+        .filter(|node| write_nodes.contains(&node.get().kind))
+        .filter_map(|node| {
+            let written = node
+                .get_descendants()
+                .into_iter()
+                .find(|descendant| descendant.get().linked_symbol.is_some())?;
+            let symbol = get_symbol(written.get().linked_symbol.as_ref()?)?;
+            if !symbol.readonly {
+                return None;
+            }
+
+            Some(Diagnostic::new(
+                written.get().range,
+                Some(DiagnosticSeverity::ERROR),
+                Some(NumberOrString::String("readonly".to_string())),
+                Some("Symbol table".to_string()),
+                format!(
+                    "Cannot assign to {} `{}`, it is read-only.",
+                    symbol.kind, symbol.name
+                ),
+                None,
+                None,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use indextree::{Arena, NodeId};
+    use tower_lsp::lsp_types::{Position, Range};
+
+    use super::find_readonly_writes;
+    use crate::language_def::Symbol as SymbolRule;
+    use crate::project::metadata::test_utils::{node, range, symbol};
+    use crate::project::metadata::{Node, SymbolId};
+    use crate::project::{NodeKind, VisitNode};
+
+    #[test]
+    fn test_write_to_in_parameter() {
+        let usage = |content, line, symbol_id| Node {
+            linked_symbol: Some(symbol_id),
+            ..node("Usage", content, SymbolRule::None, range(line, 4, 12))
+        };
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 4, 12)));
+        let scope: NodeId = arena.new_node(node("Scope", "", SymbolRule::None, range(0, 4, 12)));
+        let in_parameter = SymbolId::new(None, scope, 0);
+        let inout_parameter = SymbolId::new(None, scope, 1);
+
+        // `port = 1;`
+        let write = arena.new_node(node("Lvalue", "port", SymbolRule::None, range(1, 4, 12)));
+        write.append(
+            arena.new_node(usage("port", 1, in_parameter.clone())),
+            &mut arena,
+        );
+        root.append(write, &mut arena);
+        // `meta = port;`
+        let write = arena.new_node(node("Lvalue", "meta", SymbolRule::None, range(2, 4, 12)));
+        write.append(
+            arena.new_node(usage("meta", 2, inout_parameter.clone())),
+            &mut arena,
+        );
+        root.append(write, &mut arena);
+        root.append(arena.new_node(usage("port", 2, in_parameter)), &mut arena);
+
+        let parameter = |name: &str, readonly: bool| {
+            let mut symbol = symbol(name, "Parameter", Range::default());
+            symbol.readonly = readonly;
+            symbol
+        };
+        let diagnostics = find_readonly_writes(
+            &VisitNode::new(&arena, root),
+            &[NodeKind::Node(String::from("Lvalue"))],
+            |symbol_id| match symbol_id.index {
+                0 => Some(parameter("port", true)),
+                _ => Some(parameter("meta", false)),
+            },
+        );
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].range.start, Position::new(1, 4));
+        assert_eq!(
+            diagnostics[0].message,
+            "Cannot assign to Parameter `port`, it is read-only."
+        );
+    }
+}
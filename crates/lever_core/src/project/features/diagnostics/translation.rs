@@ -0,0 +1,34 @@
+use std::sync::{Arc, Mutex};
+
+use crate::project::{AstQuery, SymbolTableQuery};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use super::provider::DiagnosticProvider;
+
+pub struct Translation {}
+
+impl DiagnosticProvider for Translation {
+    fn get_diagnostics(
+        _uri: &Url,
+        ast_query: &Arc<Mutex<impl AstQuery>>,
+        _symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    ) -> Vec<Diagnostic> {
+        ast_query
+            .lock()
+            .unwrap()
+            .get_translation_errors()
+            .into_iter()
+            .map(|error| {
+                Diagnostic::new(
+                    error.range,
+                    Some(DiagnosticSeverity::ERROR),
+                    Some(NumberOrString::String("translation".to_string())),
+                    Some("AST".to_string()),
+                    error.message,
+                    None,
+                    None,
+                )
+            })
+            .collect()
+    }
+}
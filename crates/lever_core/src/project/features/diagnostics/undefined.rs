@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex};
+
+use crate::project::{AstQuery, SymbolTableQuery, Visitable};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Range, Url};
+
+use super::provider::DiagnosticProvider;
+
+pub struct Undefined {}
+
+impl DiagnosticProvider for Undefined {
+    fn get_diagnostics(
+        _uri: &Url,
+        ast_query: &Arc<Mutex<impl AstQuery>>,
+        symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    ) -> Vec<Diagnostic> {
+        let undefined_members = symbol_table_query.lock().unwrap().get_undefined_members();
+
+        get_undefined_usages(ast_query, symbol_table_query)
+            .into_iter()
+            .map(|(name, range)| undefined(range, format!("Undefined symbol `{name}`.")))
+            .chain(
+                undefined_members
+                    .into_iter()
+                    .map(|(name, range)| undefined(range, format!("Undefined field `{name}`."))),
+            )
+            .collect()
+    }
+}
+
+fn undefined(range: Range, message: String) -> Diagnostic {
+    Diagnostic::new(
+        range,
+        Some(DiagnosticSeverity::ERROR),
+        Some(NumberOrString::String("undefined".to_string())),
+        Some("Symbol table".to_string()),
+        message,
+        None,
+        None,
+    )
+}
+
+/// Names and ranges of the usages that no symbol, local or imported, resolves.
+pub fn get_undefined_usages(
+    ast_query: &Arc<Mutex<impl AstQuery>>,
+    symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+) -> Vec<(String, Range)> {
+    let unlinked_symbols = symbol_table_query.lock().unwrap().get_unlinked_symbols();
+    let ast_query = ast_query.lock().unwrap();
+    let root_visit = ast_query.visit_root();
+
+    unlinked_symbols
+        .into_iter()
+        // Usages resolved by an imported file are linked after the symbol table is built
+        .filter(|(_, range)| {
+            root_visit
+                .get_node_at_position(range.start)
+                .is_none_or(|node| node.get().linked_symbol.is_none())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use indextree::Arena;
+
+    use super::Undefined;
+    use crate::language_def::{self, Symbol as SymbolRule};
+    use crate::project::features::diagnostics::provider::DiagnosticProvider;
+    use crate::project::metadata::test_utils::{init, node, range, test_url};
+    use crate::project::metadata::{Ast, AstManager, SymbolTableManager};
+
+    #[test]
+    fn test_undefined_select_key_field() {
+        language_def::load_test_definition();
+
+        // struct headers_t { bit<16> ethernet; }
+        // headers_t hdr;
+        // select(hdr.ethernet, hdr.missing)
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let declaration = arena.new_node(node("Struct", "", init("Type", None), range(0, 0, 38)));
+        declaration.append(
+            arena.new_node(node("Name", "headers_t", SymbolRule::None, range(0, 7, 16))),
+            &mut arena,
+        );
+        let field = arena.new_node(node("Field", "", init("Instance", None), range(0, 19, 36)));
+        field.append(
+            arena.new_node(node("Name", "ethernet", SymbolRule::None, range(0, 27, 35))),
+            &mut arena,
+        );
+        declaration.append(field, &mut arena);
+        root.append(declaration, &mut arena);
+        let instance = arena.new_node(node(
+            "Instance",
+            "",
+            init("Instance", Some("Type")),
+            range(1, 0, 14),
+        ));
+        instance.append(
+            arena.new_node(node("Type", "headers_t", SymbolRule::Usage, range(1, 0, 9))),
+            &mut arena,
+        );
+        instance.append(
+            arena.new_node(node("Name", "hdr", SymbolRule::None, range(1, 10, 13))),
+            &mut arena,
+        );
+        root.append(instance, &mut arena);
+        let select = arena.new_node(node("Select", "", SymbolRule::None, range(2, 0, 33)));
+        let keys = [
+            ("hdr", SymbolRule::Usage, range(2, 7, 10)),
+            ("ethernet", SymbolRule::MemberUsage, range(2, 11, 19)),
+            ("hdr", SymbolRule::Usage, range(2, 21, 24)),
+            ("missing", SymbolRule::MemberUsage, range(2, 25, 32)),
+        ];
+        for (content, symbol, range) in keys {
+            select.append(
+                arena.new_node(node("Key", content, symbol, range)),
+                &mut arena,
+            );
+        }
+        root.append(select, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let symbol_table = Arc::new(Mutex::new(SymbolTableManager::new(&mut ast, test_url())));
+        let ast = Arc::new(Mutex::new(AstManager { ast }));
+
+        let diagnostics: Vec<_> = Undefined::get_diagnostics(&test_url(), &ast, &symbol_table)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.range))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![(String::from("Undefined field `missing`."), range(2, 25, 32))]
+        );
+    }
+}
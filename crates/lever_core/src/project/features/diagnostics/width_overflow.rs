@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use crate::language_def::{LanguageDefinition, WidthCheck};
+use crate::project::{AstQuery, NodeKind, SymbolTableQuery, VisitNode, Visitable};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Url};
+
+use super::provider::DiagnosticProvider;
+
+pub struct WidthOverflow {}
+
+impl DiagnosticProvider for WidthOverflow {
+    fn get_diagnostics(
+        _uri: &Url,
+        ast_query: &Arc<Mutex<impl AstQuery>>,
+        _symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
+    ) -> Vec<Diagnostic> {
+        let checks = &LanguageDefinition::get().width_checks;
+        if checks.is_empty() {
+            return vec![];
+        }
+
+        let ast_query = ast_query.lock().unwrap();
+        find_overflows(&ast_query.visit_root(), checks)
+    }
+}
+
+/// Reports the literal values of the checked declarations that don't fit in the width of their
+/// type. Values and widths that aren't literals are skipped.
+fn find_overflows(root: &VisitNode, checks: &[WidthCheck]) -> Vec<Diagnostic> {
+    root.get_descendants()
+        .iter()
+        .filter_map(|node| {
+            let check = checks
+                .iter()
+                .find(|check| node.get().kind == NodeKind::Node(check.declaration_node.clone()))?;
+
+            let value = node.get_child_of_kind(NodeKind::Node(check.value_node.clone()))?;
+            let type_node = node.get_descendants().into_iter().find(|descendant| {
+                descendant.get().kind == NodeKind::Node(check.type_node.clone())
+            })?;
+            let width = type_node.get_descendants().into_iter().find(|descendant| {
+                descendant.get().kind == NodeKind::Node(check.width_node.clone())
+            })?;
+
+            let number = parse_literal(&value.get().content)?;
+            let width: u32 = width.get().content.trim().parse().ok()?;
+            if fits(number, width, check.signed) {
+                return None;
+            }
+
+            Some(Diagnostic::new(
+                value.get().range,
+                Some(DiagnosticSeverity::WARNING),
+                Some(NumberOrString::String("width".to_string())),
+                Some("Width check".to_string()),
+                format!(
+                    "Value {number} does not fit in {}.",
+                    type_node.get().content.trim()
+                ),
+                None,
+                None,
+            ))
+        })
+        .collect()
+}
+
+/// Value of a decimal, hexadecimal (`0x`), octal (`0o`) or binary (`0b`) literal.
+fn parse_literal(text: &str) -> Option<u128> {
+    let text = text.trim().replace('_', "");
+    let lowercase = text.to_lowercase();
+
+    match lowercase.get(..2) {
+        Some("0x") => u128::from_str_radix(&lowercase[2..], 16).ok(),
+        Some("0o") => u128::from_str_radix(&lowercase[2..], 8).ok(),
+        Some("0b") => u128::from_str_radix(&lowercase[2..], 2).ok(),
+        _ => lowercase.parse().ok(),
+    }
+}
+
+fn fits(number: u128, width: u32, signed: bool) -> bool {
+    let bits = if signed {
+        width.saturating_sub(1)
+    } else {
+        width
+    };
+
+    bits >= 128 || number < 1 << bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_overflows, fits, parse_literal};
+    use crate::language_def::{Symbol as SymbolRule, WidthCheck};
+    use crate::project::metadata::test_utils::{node, range};
+    use crate::project::VisitNode;
+    use indextree::Arena;
+
+    #[test]
+    fn test_constant_too_large_for_width() {
+        // const bit<8> x = 256;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 21)));
+        let constant = arena.new_node(node(
+            "Constant",
+            "const bit<8> x = 256;",
+            SymbolRule::None,
+            range(0, 0, 21),
+        ));
+        root.append(constant, &mut arena);
+        let bit_type = arena.new_node(node("BitType", "bit<8>", SymbolRule::None, range(0, 6, 12)));
+        bit_type.append(
+            arena.new_node(node("Width", "8", SymbolRule::None, range(0, 10, 11))),
+            &mut arena,
+        );
+        constant.append(bit_type, &mut arena);
+        constant.append(
+            arena.new_node(node("Value", "256", SymbolRule::None, range(0, 17, 20))),
+            &mut arena,
+        );
+
+        let check = WidthCheck {
+            declaration_node: String::from("Constant"),
+            type_node: String::from("BitType"),
+            width_node: String::from("Width"),
+            value_node: String::from("Value"),
+            signed: false,
+        };
+        let diagnostics = find_overflows(&VisitNode::new(&arena, root), &[check]);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Value 256 does not fit in bit<8>.");
+        assert_eq!(diagnostics[0].range, range(0, 17, 20));
+
+        assert_eq!(parse_literal("0xFF"), Some(255));
+        assert_eq!(parse_literal("hdr.ttl"), None);
+        assert!(fits(255, 8, false));
+        assert!(!fits(128, 8, true));
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::{Arc, Mutex};
+
+use tower_lsp::lsp_types::{DocumentSymbol, Location, SymbolInformation, SymbolKind, Url};
+
+use crate::{
+    language_def::LanguageDefinition,
+    lsp_mappings::OutlineType,
+    project::{
+        metadata::{ScopeId, Symbol},
+        SymbolTableQuery,
+    },
+};
+
+pub fn get_nested(st_query: &Arc<Mutex<impl SymbolTableQuery>>) -> Vec<DocumentSymbol> {
+    let query = st_query.lock().unwrap();
+    let (_, root_id) = query.get_symbols_at_root();
+
+    build_nested(&*query, root_id)
+}
+
+pub fn get_flat(uri: &Url, st_query: &Arc<Mutex<impl SymbolTableQuery>>) -> Vec<SymbolInformation> {
+    let mut symbols = vec![];
+    flatten(uri, get_nested(st_query), None, &mut symbols);
+
+    symbols
+}
+
+#[allow(deprecated)] // DocumentSymbol::deprecated has to be initialized
+fn build_nested(query: &impl SymbolTableQuery, scope_id: ScopeId) -> Vec<DocumentSymbol> {
+    let mut symbols = get_scope_symbols(query, scope_id);
+    symbols.sort_by_key(|symbol| symbol.def_range.start);
+
+    symbols
+        .into_iter()
+        .map(|symbol| {
+            let (range, children) = match symbol.field_scope_id {
+                Some(field_scope_id) => (
+                    query
+                        .get_scope_range(field_scope_id)
+                        .unwrap_or(symbol.def_range),
+                    Some(build_nested(query, field_scope_id)),
+                ),
+                None => (symbol.def_range, None),
+            };
+
+            DocumentSymbol {
+                kind: get_symbol_kind(&symbol.kind),
+                detail: Some(symbol.kind),
+                name: symbol.name,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: symbol.def_range,
+                children,
+            }
+        })
+        .collect()
+}
+
+// Symbols of subscopes that don't belong to a symbol (e.g. blocks) are shown in their parent
+fn get_scope_symbols(query: &impl SymbolTableQuery, scope_id: ScopeId) -> Vec<Symbol> {
+    let mut symbols = query.get_symbols_in_scope(scope_id);
+
+    let anonymous_subscopes: Vec<ScopeId> = query
+        .get_subscopes(scope_id)
+        .into_iter()
+        .filter(|subscope_id| {
+            !symbols
+                .iter()
+                .any(|symbol| symbol.field_scope_id == Some(*subscope_id))
+        })
+        .collect();
+
+    for subscope_id in anonymous_subscopes {
+        symbols.append(&mut get_scope_symbols(query, subscope_id));
+    }
+
+    symbols
+}
+
+#[allow(deprecated)] // SymbolInformation::deprecated has to be initialized
+fn flatten(
+    uri: &Url,
+    symbols: Vec<DocumentSymbol>,
+    container_name: Option<&str>,
+    result: &mut Vec<SymbolInformation>,
+) {
+    for symbol in symbols {
+        result.push(SymbolInformation {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            tags: None,
+            deprecated: None,
+            location: Location::new(uri.clone(), symbol.range),
+            container_name: container_name.map(str::to_string),
+        });
+
+        if let Some(children) = symbol.children {
+            flatten(uri, children, Some(&symbol.name), result);
+        }
+    }
+}
+
+pub fn get_symbol_kind(symbol_kind: &str) -> SymbolKind {
+    LanguageDefinition::get()
+        .symbol_types
+        .iter()
+        .find(|symbol_type| symbol_type.name == symbol_kind)
+        .map_or(SymbolKind::VARIABLE, |symbol_type| {
+            symbol_type.outline_type.as_ref().map_or_else(
+                || symbol_type.completion_type.get_symbol_kind(),
+                OutlineType::get,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use indextree::Arena;
+    use tower_lsp::lsp_types::{Position, Range, SymbolKind};
+
+    use super::{get_nested, get_symbol_kind};
+    use crate::language_def::{self, Symbol as SymbolRule};
+    use crate::project::metadata::test_utils::{init, node, range, test_url};
+    use crate::project::metadata::{Ast, SymbolTableManager};
+
+    fn lines(start_line: u32, end_line: u32) -> Range {
+        Range::new(Position::new(start_line, 0), Position::new(end_line, 1))
+    }
+
+    #[test]
+    fn test_nested_outline() {
+        language_def::load_test_definition();
+
+        // control C() {
+        //     action a() {}
+        // }
+        let mut arena = Arena::new();
+        let name = |content, line| node("Name", content, SymbolRule::None, range(line, 0, 1));
+        let root = arena.new_node(node("Root", "", SymbolRule::None, lines(0, 2)));
+        let control = arena.new_node(node("Control", "", init("Control", None), lines(0, 2)));
+        control.append(arena.new_node(name("C", 0)), &mut arena);
+        let action = arena.new_node(node("Action", "", init("Action", None), lines(1, 1)));
+        action.append(arena.new_node(name("a", 1)), &mut arena);
+        control.append(action, &mut arena);
+        root.append(control, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let st_query = Arc::new(Mutex::new(SymbolTableManager::new(&mut ast, test_url())));
+
+        let outline = get_nested(&st_query);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(
+            (outline[0].name.as_str(), outline[0].kind, outline[0].range),
+            ("C", SymbolKind::CLASS, lines(0, 2))
+        );
+        let children = outline[0].children.as_ref().unwrap();
+        assert_eq!(children.len(), 1);
+        assert_eq!(
+            (children[0].name.as_str(), children[0].kind),
+            ("a", SymbolKind::METHOD)
+        );
+    }
+
+    #[test]
+    fn test_outline_type_override() {
+        language_def::load_test_definition();
+
+        assert_eq!(get_symbol_kind("Action"), SymbolKind::METHOD);
+        assert_eq!(get_symbol_kind("Control"), SymbolKind::CLASS);
+        assert_eq!(get_symbol_kind("Table"), SymbolKind::VARIABLE);
+    }
+}
@@ -0,0 +1,93 @@
+use tower_lsp::lsp_types::{FoldingRange, FoldingRangeKind, Range};
+
+use crate::project::metadata::Comment;
+
+/// Folding ranges of the scopes spanning several lines, from their opening to their closing brace.
+pub fn scope_folds(scope_ranges: &[Range], source_code: &str) -> Vec<FoldingRange> {
+    let lines: Vec<&str> = source_code.lines().collect();
+
+    scope_ranges
+        .iter()
+        .filter_map(|range| {
+            let start_line = opening_brace_line(&lines, *range).unwrap_or(range.start.line);
+            let end_line = range.end.line;
+            (end_line > start_line).then(|| fold(start_line, end_line, FoldingRangeKind::Region))
+        })
+        .collect()
+}
+
+/// Folding ranges of the block comments.
+pub fn comment_folds(comments: &[Comment]) -> Vec<FoldingRange> {
+    comments
+        .iter()
+        .filter(|comment| comment.end_line > comment.start_line)
+        .map(|comment| {
+            fold(
+                comment.start_line,
+                comment.end_line,
+                FoldingRangeKind::Comment,
+            )
+        })
+        .collect()
+}
+
+// A scope's range starts at its declaration, which may put the brace on a later line
+fn opening_brace_line(lines: &[&str], range: Range) -> Option<u32> {
+    (range.start.line..=range.end.line).find(|line| {
+        let Some(text) = lines.get(*line as usize) else {
+            return false;
+        };
+        let from = if *line == range.start.line {
+            range.start.character as usize
+        } else {
+            0
+        };
+        text.get(from..).is_some_and(|text| text.contains('{'))
+    })
+}
+
+fn fold(start_line: u32, end_line: u32, kind: FoldingRangeKind) -> FoldingRange {
+    FoldingRange {
+        start_line,
+        start_character: None,
+        end_line,
+        end_character: None,
+        kind: Some(kind),
+        collapsed_text: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Position;
+
+    use super::*;
+
+    #[test]
+    fn test_scope_and_comment_folds() {
+        let source_code = "control C(\n    in bit<8> x)\n{\n    apply { }\n}\n";
+        let control = Range::new(Position::new(0, 0), Position::new(4, 1));
+        let apply = Range::new(Position::new(3, 4), Position::new(3, 13));
+
+        let folds = scope_folds(&[control, apply], source_code);
+        assert_eq!(folds.len(), 1);
+        assert_eq!((folds[0].start_line, folds[0].end_line), (2, 4));
+        assert_eq!(folds[0].kind, Some(FoldingRangeKind::Region));
+
+        let comments = vec![
+            Comment {
+                start_line: 0,
+                end_line: 2,
+                text: "/* a\n b\n */".into(),
+            },
+            Comment {
+                start_line: 4,
+                end_line: 4,
+                text: "// c".into(),
+            },
+        ];
+        let folds = comment_folds(&comments);
+        assert_eq!(folds.len(), 1);
+        assert_eq!(folds[0].kind, Some(FoldingRangeKind::Comment));
+    }
+}
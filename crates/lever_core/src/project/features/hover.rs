@@ -0,0 +1,196 @@
+use tower_lsp::lsp_types::{HoverContents, MarkedString, Position};
+
+use crate::language_def::BaseType;
+use crate::project::metadata::Symbol;
+use crate::utils;
+
+/// Whether there can be something to say about the position: it is on a character of the code or
+/// right after one, outside of comments. `node_kind` is the kind of the innermost syntax node at
+/// the position.
+pub fn is_hoverable(
+    source_code: &str,
+    position: Position,
+    node_kind: Option<&str>,
+    comment_nodes: &[String],
+) -> bool {
+    let Some(line) = source_code.lines().nth(position.line as usize) else {
+        return false;
+    };
+    let Some(byte) = utils::utf16_to_byte(line, position.character) else {
+        return false;
+    };
+    let is_code = |c: Option<char>| c.is_some_and(|c| !c.is_whitespace());
+    let on_code = is_code(line[byte..].chars().next()) || is_code(line[..byte].chars().last());
+
+    on_code && node_kind.is_some_and(|kind| !comment_nodes.iter().any(|comment| comment == kind))
+}
+
+/// Formats the hover content of a symbol. `resolved_type` is only given when the written type
+/// is an alias of another type.
+pub fn get_symbol_info(
+    symbol: &Symbol,
+    written_type: Option<&Symbol>,
+    resolved_type: Option<&Symbol>,
+) -> HoverContents {
+    let mut lines: Vec<String> = symbol.documentation.iter().cloned().collect();
+    lines.extend(symbol.annotations.iter().map(|annotation| {
+        // Shown with the value of its argument, `@name("a\"b")` as `@name: a"b`
+        utils::parse_annotation(annotation).map_or_else(
+            || annotation.clone(),
+            |(name, argument)| format!("@{name}: {argument}"),
+        )
+    }));
+
+    lines.push(match (written_type, resolved_type) {
+        (Some(written_type), Some(resolved_type)) => format!(
+            "{}: {} (resolves to {})",
+            symbol.name, written_type.name, resolved_type.name
+        ),
+        (Some(written_type), None) => format!("{}: {}", symbol.name, written_type.name),
+        (None, _) => format!("{} {}", symbol.kind, symbol.name),
+    });
+    if let Some(expansion) = &symbol.expansion {
+        lines.push(format!("Expands to: {expansion}"));
+    }
+
+    HoverContents::Scalar(MarkedString::String(lines.join("\n")))
+}
+
+/// Formats the hover content of a base type. `width` is the text of its width node, which can be
+/// a literal or an expression.
+pub fn get_base_type_info(base_type: &BaseType, width: Option<&str>) -> HoverContents {
+    HoverContents::Scalar(MarkedString::String(
+        base_type
+            .description
+            .replace("{width}", width.unwrap_or("?")),
+    ))
+}
+
+/// Appends the source line declaring the symbol as a code block in `language`.
+pub fn append_declaration(contents: HoverContents, language: &str, line: &str) -> HoverContents {
+    let declaration = format!("```{language}\n{}\n```", line.trim());
+
+    let text = match contents {
+        HoverContents::Scalar(MarkedString::String(text)) => format!("{text}\n{declaration}"),
+        _ => declaration,
+    };
+    HoverContents::Scalar(MarkedString::String(text))
+}
+
+/// Describes what's under the cursor when it isn't a symbol: a keyword or a usage that doesn't
+/// resolve.
+pub fn get_fallback_info(word: &str, is_keyword: bool) -> HoverContents {
+    let text = if is_keyword {
+        format!("Keyword `{word}`")
+    } else {
+        format!("`{word}` isn't declared")
+    };
+    HoverContents::Scalar(MarkedString::String(text))
+}
+
+/// Appends the kind and byte range of the syntax node to the hover content, if any.
+pub fn append_node_info(
+    contents: Option<HoverContents>,
+    node: &tree_sitter::Node,
+) -> HoverContents {
+    let node_info = format!(
+        "{} [{}..{}]",
+        node.kind(),
+        node.start_byte(),
+        node.end_byte()
+    );
+
+    let text = match contents {
+        Some(HoverContents::Scalar(MarkedString::String(text))) => format!("{text}\n{node_info}"),
+        _ => node_info,
+    };
+    HoverContents::Scalar(MarkedString::String(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{HoverContents, MarkedString, Position, Range};
+
+    use super::{append_declaration, get_base_type_info, get_symbol_info, is_hoverable};
+    use crate::language_def::BaseType;
+    use crate::project::metadata::test_utils::symbol;
+
+    #[test]
+    fn test_annotations_in_hover() {
+        let mut table = symbol("forward", "Table", Range::default());
+        table.annotations = vec![
+            String::from(r#"@name("ingress.\"forward\"\\")"#),
+            String::from("@hidden"),
+        ];
+
+        assert_eq!(
+            get_symbol_info(&table, None, None),
+            HoverContents::Scalar(MarkedString::String(String::from(
+                "@name: ingress.\"forward\"\\\n@hidden\nTable forward"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_declaration_in_hover() {
+        let constant = symbol("MAX_PORTS", "Constant", Range::default());
+
+        assert_eq!(
+            append_declaration(
+                get_symbol_info(&constant, None, None),
+                "p4",
+                "    const bit<9> MAX_PORTS = 64;"
+            ),
+            HoverContents::Scalar(MarkedString::String(String::from(
+                "Constant MAX_PORTS\n```p4\nconst bit<9> MAX_PORTS = 64;\n```"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_nothing_to_hover() {
+        let source = "control C() {\n    // Drops\n    apply { drop(); }\n}";
+        let comment_nodes = vec![String::from("comment")];
+        let hoverable = |line, character, node_kind| {
+            is_hoverable(
+                source,
+                Position::new(line, character),
+                Some(node_kind),
+                &comment_nodes,
+            )
+        };
+
+        assert!(hoverable(2, 14, "identifier"));
+        // Right after `drop`
+        assert!(hoverable(2, 16, "identifier"));
+        assert!(!hoverable(2, 2, "block"));
+        assert!(!hoverable(1, 8, "comment"));
+        assert!(!hoverable(0, 40, "source_file"));
+        assert!(!hoverable(9, 0, "source_file"));
+    }
+
+    #[test]
+    fn test_hover_after_non_ascii_characters() {
+        // Positions count UTF-16 code units, `é` is one but takes two bytes
+        let source = "const string s = \"é\";  x";
+        let hoverable =
+            |character| is_hoverable(source, Position::new(0, character), Some("identifier"), &[]);
+
+        assert!(!hoverable(22));
+        assert!(hoverable(23));
+    }
+
+    #[test]
+    fn test_base_type_with_symbolic_width() {
+        let bit = BaseType {
+            node_name: String::from("bit_type"),
+            description: String::from("unsigned {width}-bit integer"),
+            width_field: Some(String::from("width")),
+        };
+
+        assert_eq!(
+            get_base_type_info(&bit, Some("W")),
+            HoverContents::Scalar(MarkedString::String(String::from("unsigned W-bit integer")))
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::{Arc, Mutex};
+
+use tower_lsp::lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Range};
+
+use crate::language_def::{CallRule, LanguageDefinition};
+use crate::project::{metadata::SymbolId, AstQuery, NodeKind, VisitNode, Visitable};
+
+/// A call of a symbol and its arguments, in order.
+pub struct Call {
+    pub rule: &'static CallRule,
+    pub range: Range,
+    pub callee: SymbolId,
+    pub arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Argument {
+    pub range: Range,
+    pub content: String,
+    // Argument already naming its parameter, as in `f(ttl = 64)`
+    pub named: bool,
+}
+
+/// Calls intersecting the range whose callee is linked to a symbol.
+pub fn get_calls(range: Range, ast_query: &Arc<Mutex<impl AstQuery>>) -> Vec<Call> {
+    let rules = &LanguageDefinition::get().call_rules;
+    let ast_query = ast_query.lock().unwrap();
+    let root_visit = ast_query.visit_root();
+
+    root_visit
+        .get_descendants()
+        .into_iter()
+        .filter(|node| intersects(node.get().range, range))
+        .filter_map(|node| {
+            let NodeKind::Node(name) = &node.get().kind else {
+                return None;
+            };
+            let rule = rules.iter().find(|rule| &rule.call_node == name)?;
+
+            // In `hdr.ipv4.setValid()`, the last member names the callee
+            let callee = node
+                .get_child_of_kind(NodeKind::Node(rule.callee_node.clone()))?
+                .get_descendants()
+                .iter()
+                .rev()
+                .find_map(|descendant| descendant.get().linked_symbol.clone())?;
+
+            let mut arguments = vec![];
+            collect_arguments(rule, node, &mut arguments);
+            arguments.sort_by_key(|argument| argument.range.start);
+
+            Some(Call {
+                rule,
+                range: node.get().range,
+                callee,
+                arguments,
+            })
+        })
+        .collect()
+}
+
+// Arguments of nested calls belong to those calls
+fn collect_arguments(rule: &CallRule, node: VisitNode, arguments: &mut Vec<Argument>) {
+    for child in node.get_children() {
+        let NodeKind::Node(name) = &child.get().kind else {
+            continue;
+        };
+
+        if name == &rule.argument_node {
+            arguments.push(Argument {
+                range: child.get().range,
+                content: child.get().content.trim().to_string(),
+                named: rule.argument_name_node.as_ref().is_some_and(|name_node| {
+                    child
+                        .get_child_of_kind(NodeKind::Node(name_node.clone()))
+                        .is_some()
+                }),
+            });
+        } else if name != &rule.call_node {
+            collect_arguments(rule, child, arguments);
+        }
+    }
+}
+
+fn intersects(a: Range, b: Range) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// Hints naming the parameter of each positional argument. Named arguments and arguments
+/// already spelling the name of their parameter get no hint.
+pub fn parameter_hints(arguments: &[Argument], parameters: &[String]) -> Vec<InlayHint> {
+    arguments
+        .iter()
+        .zip(parameters)
+        .filter(|(argument, parameter)| {
+            // `meta.ttl` passed as `ttl` is clear enough
+            !argument.named && argument.content.rsplit('.').next() != Some(parameter.as_str())
+        })
+        .map(|(argument, parameter)| InlayHint {
+            position: argument.range.start,
+            label: InlayHintLabel::String(format!("{parameter}:")),
+            kind: Some(InlayHintKind::PARAMETER),
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: Some(true),
+            data: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{InlayHintLabel, Position, Range};
+
+    use super::{parameter_hints, Argument};
+
+    fn argument(content: &str, start: u32) -> Argument {
+        Argument {
+            range: Range::new(
+                Position::new(3, start),
+                Position::new(3, start + content.len() as u32),
+            ),
+            content: content.to_string(),
+            named: false,
+        }
+    }
+
+    #[test]
+    fn test_action_call_hints() {
+        // set_ttl(64, port);
+        let arguments = vec![argument("64", 16), argument("port", 20)];
+        let parameters = vec![String::from("ttl"), String::from("port")];
+
+        let hints = parameter_hints(&arguments, &parameters);
+
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].position, Position::new(3, 16));
+        assert!(matches!(&hints[0].label, InlayHintLabel::String(label) if label == "ttl:"));
+
+        let named = vec![Argument {
+            named: true,
+            ..argument("ttl = 64", 16)
+        }];
+        assert!(parameter_hints(&named, &parameters).is_empty());
+    }
+}
@@ -1,3 +1,11 @@
+pub mod code_actions;
 pub mod completion;
 pub mod diagnostics;
+pub mod document_symbols;
+pub mod folding;
+pub mod hover;
+pub mod inlay_hints;
+pub mod references;
 pub mod semantic_tokens;
+pub mod signature_help;
+pub mod workspace_symbols;
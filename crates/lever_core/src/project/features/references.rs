@@ -0,0 +1,203 @@
+use serde::Serialize;
+use tower_lsp::lsp_types::{DocumentHighlight, DocumentHighlightKind, Location, Range, Url};
+
+/// References found in one file.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct FileReferences {
+    pub uri: Url,
+    pub references: Vec<ReferencePreview>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ReferencePreview {
+    pub range: Range,
+    // Trimmed source line of the reference
+    pub preview: String,
+}
+
+/// Locations of the usages, after the declaration when it is included.
+pub fn with_declaration(
+    declaration: Location,
+    usages: Vec<Location>,
+    include_declaration: bool,
+) -> Vec<Location> {
+    if !include_declaration {
+        return usages;
+    }
+
+    let mut locations = vec![declaration];
+    locations.extend(usages);
+    locations
+}
+
+/// Highlights of the occurrences in the file `url`: the declaration is written, the usages read.
+pub fn highlights(
+    url: &Url,
+    declaration: Location,
+    usages: Vec<Location>,
+) -> Vec<DocumentHighlight> {
+    let declaration = (declaration, DocumentHighlightKind::WRITE);
+    let usages = usages
+        .into_iter()
+        .map(|usage| (usage, DocumentHighlightKind::READ));
+
+    std::iter::once(declaration)
+        .chain(usages)
+        .filter(|(location, _)| &location.uri == url)
+        .map(|(location, kind)| DocumentHighlight {
+            range: location.range,
+            kind: Some(kind),
+        })
+        .collect()
+}
+
+/// Groups the locations by file, in the order the files first appear, with the references of a
+/// file sorted by position. `get_source` returns the source code of a file.
+pub fn group_by_file<'a>(
+    locations: Vec<Location>,
+    get_source: impl Fn(&Url) -> Option<&'a str>,
+) -> Vec<FileReferences> {
+    let mut groups: Vec<FileReferences> = vec![];
+
+    for location in locations {
+        let preview = get_source(&location.uri)
+            .and_then(|source| source.lines().nth(location.range.start.line as usize))
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        let reference = ReferencePreview {
+            range: location.range,
+            preview,
+        };
+
+        match groups.iter_mut().find(|group| group.uri == location.uri) {
+            Some(group) => group.references.push(reference),
+            None => groups.push(FileReferences {
+                uri: location.uri,
+                references: vec![reference],
+            }),
+        }
+    }
+
+    for group in &mut groups {
+        group
+            .references
+            .sort_by_key(|reference| reference.range.start);
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::{Location, Position, Range, Url};
+
+    use tower_lsp::lsp_types::DocumentHighlightKind;
+
+    use super::{group_by_file, highlights, with_declaration, FileReferences, ReferencePreview};
+
+    fn reference(line: u32, character: u32, preview: &str) -> ReferencePreview {
+        ReferencePreview {
+            range: Range::new(
+                Position::new(line, character),
+                Position::new(line, character + 4),
+            ),
+            preview: preview.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_references_grouped_by_file() {
+        let main = Url::parse("file:///main.p4").unwrap();
+        let ingress = Url::parse("file:///ingress.p4").unwrap();
+        let location = |uri: &Url, line: u32, character: u32| {
+            Location::new(
+                uri.clone(),
+                Range::new(
+                    Position::new(line, character),
+                    Position::new(line, character + 4),
+                ),
+            )
+        };
+
+        let groups = group_by_file(
+            vec![
+                location(&ingress, 3, 12),
+                location(&main, 2, 4),
+                location(&ingress, 1, 8),
+            ],
+            |uri| match uri.path() {
+                "/main.p4" => Some("#include \"ingress.p4\"\n\n    drop();\n"),
+                "/ingress.p4" => {
+                    Some("control C() {\n    actions = { drop; }\n\n        apply { drop(); }\n}")
+                }
+                _ => None,
+            },
+        );
+
+        assert_eq!(
+            groups,
+            vec![
+                FileReferences {
+                    uri: ingress,
+                    references: vec![
+                        reference(1, 8, "actions = { drop; }"),
+                        reference(3, 12, "apply { drop(); }"),
+                    ],
+                },
+                FileReferences {
+                    uri: main,
+                    references: vec![reference(2, 4, "drop();")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_references_with_declaration() {
+        let main = Url::parse("file:///main.p4").unwrap();
+        let location = |line: u32| {
+            Location::new(
+                main.clone(),
+                Range::new(Position::new(line, 4), Position::new(line, 8)),
+            )
+        };
+        let usages = vec![location(3), location(5)];
+
+        assert_eq!(with_declaration(location(1), usages.clone(), false), usages);
+        assert_eq!(
+            with_declaration(location(1), usages, true),
+            vec![location(1), location(3), location(5)]
+        );
+    }
+
+    #[test]
+    fn test_highlights_in_file() {
+        let main = Url::parse("file:///main.p4").unwrap();
+        let ingress = Url::parse("file:///ingress.p4").unwrap();
+        let location = |uri: &Url, line: u32| {
+            Location::new(
+                uri.clone(),
+                Range::new(Position::new(line, 4), Position::new(line, 8)),
+            )
+        };
+
+        let highlights = highlights(
+            &main,
+            location(&main, 1),
+            vec![location(&main, 3), location(&ingress, 2)],
+        );
+
+        let kinds: Vec<(u32, Option<DocumentHighlightKind>)> = highlights
+            .iter()
+            .map(|highlight| (highlight.range.start.line, highlight.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, Some(DocumentHighlightKind::WRITE)),
+                (3, Some(DocumentHighlightKind::READ)),
+            ]
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     language_def::{self, LanguageDefinition},
-    lsp_mappings::HighlightType,
-    project::{AstQuery, SymbolTableQuery, Visitable},
+    lsp_mappings::{HighlightType, UNRESOLVED_TOKEN_TYPE},
+    project::{features::diagnostics, AstQuery, SymbolTableQuery, Visitable},
     utils,
 };
 use std::{
@@ -12,10 +12,10 @@ use tower_lsp::lsp_types::{SemanticToken, SemanticTokens, SemanticTokensResult};
 use tree_sitter::Node;
 
 pub struct ColorData {
-    line: u32,
-    start: u32,
-    length: u32,
-    node_type: u32,
+    pub line: u32,
+    pub start: u32,
+    pub length: u32,
+    pub node_type: u32,
 }
 
 pub fn get_tokens(
@@ -23,11 +23,17 @@ pub fn get_tokens(
     symbol_table_query: &Arc<Mutex<impl SymbolTableQuery>>,
     ts_tree: &tree_sitter::Tree,
     source_code: &str,
+    extra_tokens: Vec<ColorData>,
 ) -> SemanticTokensResult {
     //Getting ast data
     let mut array = get_keyword_color_data(&ts_tree.root_node(), source_code);
     array.append(&mut get_symbols_color_data(symbol_table_query));
     array.append(&mut get_ast_color_data(ast_query));
+    array = mark_unresolved(
+        array,
+        get_unresolved_color_data(ast_query, symbol_table_query),
+    );
+    array = merge_extra_tokens(array, extra_tokens);
     //sort line
 
     array.sort_by_key(|token| token.line);
@@ -91,6 +97,35 @@ pub fn get_tokens(
     })
 }
 
+/// Adds the tokens that don't overlap the core ones, which always win.
+fn merge_extra_tokens(mut core: Vec<ColorData>, extra: Vec<ColorData>) -> Vec<ColorData> {
+    let overlaps = |a: &ColorData, b: &ColorData| {
+        a.line == b.line && a.start < b.start + b.length && b.start < a.start + a.length
+    };
+
+    let extra: Vec<ColorData> = extra
+        .into_iter()
+        .filter(|token| !core.iter().any(|core_token| overlaps(core_token, token)))
+        .collect();
+    core.extend(extra);
+
+    core
+}
+
+/// Replaces the tokens overlapping unresolved usages by the tokens of those usages.
+fn mark_unresolved(mut tokens: Vec<ColorData>, unresolved: Vec<ColorData>) -> Vec<ColorData> {
+    tokens.retain(|token| {
+        !unresolved.iter().any(|usage| {
+            usage.line == token.line
+                && usage.start < token.start + token.length
+                && token.start < usage.start + usage.length
+        })
+    });
+    tokens.extend(unresolved);
+
+    tokens
+}
+
 fn get_semantic_token_map() -> HashMap<String, usize> {
     let mut semantic_token_types_map = HashMap::new();
 
@@ -172,6 +207,25 @@ fn get_symbol_highlight_type(symbol_kind: &str) -> HighlightType {
         .clone()
 }
 
+fn get_unresolved_color_data(
+    ast_query: &Arc<Mutex<impl AstQuery>>,
+    st_query: &Arc<Mutex<impl SymbolTableQuery>>,
+) -> Vec<ColorData> {
+    let node_type = *get_semantic_token_map()
+        .get(UNRESOLVED_TOKEN_TYPE.as_str())
+        .unwrap() as u32;
+
+    diagnostics::get_undefined_usages(ast_query, st_query)
+        .into_iter()
+        .map(|(_, range)| ColorData {
+            line: range.start.line,
+            start: range.start.character,
+            length: range.end.character - range.start.character,
+            node_type,
+        })
+        .collect()
+}
+
 pub fn get_ast_color_data(ast_query: &Arc<Mutex<impl AstQuery>>) -> Vec<ColorData> {
     let semantic_token_types_map = get_semantic_token_map();
 
@@ -195,3 +249,45 @@ pub fn get_ast_color_data(ast_query: &Arc<Mutex<impl AstQuery>>) -> Vec<ColorDat
 
     color_data
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{mark_unresolved, merge_extra_tokens, ColorData};
+
+    fn token(line: u32, start: u32, length: u32, node_type: u32) -> ColorData {
+        ColorData {
+            line,
+            start,
+            length,
+            node_type,
+        }
+    }
+
+    #[test]
+    fn test_core_tokens_win() {
+        let core = vec![token(0, 4, 6, 1), token(2, 0, 3, 2)];
+        let extra = vec![token(0, 8, 4, 9), token(0, 10, 4, 9), token(1, 4, 6, 9)];
+
+        let merged: Vec<(u32, u32, u32)> = merge_extra_tokens(core, extra)
+            .iter()
+            .map(|token| (token.line, token.start, token.node_type))
+            .collect();
+
+        assert_eq!(merged, vec![(0, 4, 1), (2, 0, 2), (0, 10, 9), (1, 4, 9)]);
+    }
+
+    #[test]
+    fn test_unresolved_usage_token() {
+        let unresolved_type = 7;
+        // The identifier at (1, 4) was highlighted by its node before it turned out unresolved
+        let tokens = vec![token(0, 0, 7, 0), token(1, 4, 3, 2)];
+
+        let marked: Vec<(u32, u32, u32)> =
+            mark_unresolved(tokens, vec![token(1, 4, 3, unresolved_type)])
+                .iter()
+                .map(|token| (token.line, token.start, token.node_type))
+                .collect();
+
+        assert_eq!(marked, vec![(0, 0, 0), (1, 4, unresolved_type)]);
+    }
+}
@@ -0,0 +1,79 @@
+use tower_lsp::lsp_types::{
+    ParameterInformation, ParameterLabel, SignatureHelp, SignatureInformation,
+};
+
+/// Index of the argument being written, from the source of the call up to the cursor. Commas of
+/// nested calls and brackets don't count.
+pub fn active_parameter(call_text: &str) -> u32 {
+    let Some((_, arguments)) = call_text.split_once('(') else {
+        return 0;
+    };
+
+    let mut depth = 0;
+    let mut index = 0;
+    for character in arguments.chars() {
+        match character {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => index += 1,
+            _ => {}
+        }
+    }
+    index
+}
+
+/// Signature of the callee, its parameters written as `name: type` when their type is known.
+pub fn build(
+    callee: &str,
+    parameters: &[(String, Option<String>)],
+    active_parameter: u32,
+) -> SignatureHelp {
+    let labels: Vec<String> = parameters
+        .iter()
+        .map(|(name, type_name)| match type_name {
+            Some(type_name) => format!("{name}: {type_name}"),
+            None => name.clone(),
+        })
+        .collect();
+
+    let signature = SignatureInformation {
+        label: format!("{callee}({})", labels.join(", ")),
+        documentation: None,
+        parameters: Some(
+            labels
+                .into_iter()
+                .map(|label| ParameterInformation {
+                    label: ParameterLabel::Simple(label),
+                    documentation: None,
+                })
+                .collect(),
+        ),
+        active_parameter: None,
+    };
+
+    SignatureHelp {
+        signatures: vec![signature],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{active_parameter, build};
+
+    #[test]
+    fn test_signature_of_action_call() {
+        assert_eq!(active_parameter("set_port("), 0);
+        assert_eq!(active_parameter("set_port(hdr.ipv4.ttl, f(a, b), "), 2);
+        assert_eq!(active_parameter("set_port"), 0);
+
+        let parameters = vec![
+            ("port".to_string(), Some("PortId_t".to_string())),
+            ("ttl".to_string(), None),
+        ];
+        let help = build("set_port", &parameters, 1);
+        assert_eq!(help.signatures[0].label, "set_port(port: PortId_t, ttl)");
+        assert_eq!(help.active_parameter, Some(1));
+    }
+}
@@ -0,0 +1,85 @@
+use tower_lsp::lsp_types::SymbolInformation;
+
+use super::document_symbols;
+use crate::language_def::LanguageDefinition;
+use crate::project::metadata::Symbol;
+use crate::utils;
+
+// Queries starting with it only match the names given by annotations
+const ANNOTATION_QUERY_PREFIX: &str = "@:";
+
+/// Symbols whose name contains the query, ignoring case. The names given by the language's name
+/// annotation are matched too.
+pub fn search(symbols: Vec<Symbol>, query: &str) -> Vec<SymbolInformation> {
+    let name_annotation = LanguageDefinition::get().name_annotation.as_deref();
+    let (query, annotations_only) = match query.strip_prefix(ANNOTATION_QUERY_PREFIX) {
+        Some(query) => (query, true),
+        None => (query, false),
+    };
+
+    symbols
+        .into_iter()
+        .filter(|symbol| matches(symbol, query, name_annotation, annotations_only))
+        .map(|symbol| to_information(&symbol))
+        .collect()
+}
+
+fn matches(
+    symbol: &Symbol,
+    query: &str,
+    name_annotation: Option<&str>,
+    annotations_only: bool,
+) -> bool {
+    let query = query.to_lowercase();
+    let annotated_name = name_annotation.and_then(|annotation| {
+        symbol
+            .annotations
+            .iter()
+            .find_map(|content| get_annotated_name(content, annotation))
+    });
+
+    (!annotations_only && symbol.name.to_lowercase().contains(&query))
+        || annotated_name.is_some_and(|name| name.to_lowercase().contains(&query))
+}
+
+/// Argument of an annotation like `@name("ingress.drop")` if it is named `annotation`.
+fn get_annotated_name(content: &str, annotation: &str) -> Option<String> {
+    utils::parse_annotation(content)
+        .filter(|(name, _)| *name == annotation)
+        .map(|(_, argument)| argument)
+}
+
+#[allow(deprecated)] // SymbolInformation::deprecated has to be initialized
+fn to_information(symbol: &Symbol) -> SymbolInformation {
+    SymbolInformation {
+        name: symbol.name.clone(),
+        kind: document_symbols::get_symbol_kind(&symbol.kind),
+        tags: None,
+        deprecated: None,
+        location: symbol.get_location(),
+        container_name: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tower_lsp::lsp_types::Range;
+
+    use super::{get_annotated_name, matches};
+    use crate::project::metadata::test_utils::symbol;
+
+    #[test]
+    fn test_match_by_annotation() {
+        let mut symbol = symbol("drop", "Action", Range::default());
+        symbol.annotations = vec![String::from("@name(\"ingress.discard\")")];
+
+        assert_eq!(
+            get_annotated_name(&symbol.annotations[0], "name"),
+            Some(String::from("ingress.discard"))
+        );
+        assert!(matches(&symbol, "Discard", Some("name"), false));
+        assert!(matches(&symbol, "drop", Some("name"), false));
+        assert!(!matches(&symbol, "drop", Some("name"), true));
+        assert!(!matches(&symbol, "discard", None, false));
+    }
+}
@@ -4,19 +4,28 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use tower_lsp::lsp_types::{
-    self, CompletionContext, CompletionItem, Diagnostic, Position, SemanticTokensResult,
-    TextDocumentContentChangeEvent, Url,
+    self, CompletionContext, CompletionItem, Diagnostic, DocumentSymbolResponse, HoverContents,
+    Position, SemanticTokensResult, TextDocumentContentChangeEvent, Url,
 };
-use tree_sitter::{InputEdit, Parser, Tree};
+use tree_sitter::{InputEdit, Tree};
 
-use super::features::{completion, diagnostics, semantic_tokens};
+use super::features::completion::{self, RuleContext};
+use super::features::inlay_hints::{self, Call};
+use super::features::semantic_tokens::{self, ColorData};
+use super::features::{diagnostics, document_symbols, folding, hover, signature_help};
 use super::metadata::{
-    AstEditor, AstManager, AstQuery, SymbolId, SymbolTableEditor, SymbolTableManager, Visitable,
+    collect_comments, AstEditor, AstManager, AstQuery, SymbolId, SymbolTableEditor,
+    SymbolTableManager, SymbolTableQuery, Visitable,
 };
+use super::parser_pool::ParserPool;
 
-use crate::language_def::{Import, LanguageDefinition};
+use crate::language_def::{Import, LanguageDefinition, Symbol};
 use crate::project::workspace;
-use crate::utils;
+use crate::utils::{self, ResolvedInclude};
+
+// A resolved import and the range of its directive, or the range and name of an unresolved one
+pub type ImportPath =
+    Result<(workspace::Import, ResolvedInclude, lsp_types::Range), (lsp_types::Range, String)>;
 
 pub struct File {
     pub uri: Url,
@@ -24,30 +33,47 @@ pub struct File {
     pub tree: Tree,
     pub symbol_table_manager: Arc<Mutex<SymbolTableManager>>,
     pub ast_manager: Arc<Mutex<AstManager>>,
-    parser: tree_sitter::Parser,
+    indexed: bool,
 }
 
 // Mainly used for debugging File graph
 impl Debug for File {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("File")
-            .field("name", &self.uri.path_segments().unwrap().last().unwrap())
+            .field(
+                "name",
+                // Documents like `untitled:` ones have an opaque path instead of segments
+                &self
+                    .uri
+                    .path_segments()
+                    .and_then(|mut segments| segments.next_back())
+                    .unwrap_or(self.uri.path()),
+            )
             .finish_non_exhaustive()
     }
 }
 
 impl File {
-    pub fn new(uri: Url, source_code: &str, tree_sitter_language: tree_sitter::Language) -> File {
-        let mut parser = Parser::new();
-        parser.set_language(tree_sitter_language).unwrap();
-
-        let tree = parser.parse(source_code, None).unwrap();
+    /// Creates a file, only parsing and indexing its content if it isn't larger than
+    /// `max_size` bytes. Files that aren't indexed, because of their size or because they failed
+    /// to parse, keep their source code but expose no symbols.
+    pub fn new(uri: Url, source_code: &str, parser_pool: &ParserPool, max_size: usize) -> File {
+        let parsed = if source_code.len() <= max_size {
+            parser_pool.parse(source_code, None)
+        } else {
+            None
+        };
+        let indexed = parsed.is_some();
+        let tree = parsed.unwrap_or_else(|| parser_pool.parse_empty());
 
         let ast_manager = Arc::new(Mutex::new(AstManager::new(source_code, tree.clone())));
 
         let symbol_table_manager = {
             let mut ast_manager = ast_manager.lock().unwrap();
-            Arc::new(Mutex::new(SymbolTableManager::new(ast_manager.get_ast())))
+            let mut symbol_table_manager =
+                SymbolTableManager::new(ast_manager.get_ast(), uri.clone());
+            symbol_table_manager.attach_comments(&collect_comments(&tree, source_code));
+            Arc::new(Mutex::new(symbol_table_manager))
         };
 
         debug!("\nAST:\n{}", ast_manager.lock().unwrap());
@@ -59,123 +85,157 @@ impl File {
             tree,
             symbol_table_manager,
             ast_manager,
-            parser,
+            indexed,
         }
     }
 
-    pub fn update(&mut self, changes: Vec<TextDocumentContentChangeEvent>) {
+    pub const fn is_indexed(&self) -> bool {
+        self.indexed
+    }
+
+    /// Applies the changes to the source code. The tree is only edited incrementally when the
+    /// file was indexed before and still is afterwards, otherwise it is parsed from scratch.
+    pub fn update(
+        &mut self,
+        changes: Vec<TextDocumentContentChangeEvent>,
+        parser_pool: &ParserPool,
+        max_size: usize,
+    ) {
+        let mut incremental = self.indexed;
+        // Tree before the changes, edited like the text so that it can be compared with the final
+        // tree. None once the whole file was parsed again
+        let mut old_tree = incremental.then(|| self.tree.clone());
+
         for change in changes {
-            let mut old_tree: Option<&Tree> = None;
-            let text: String;
+            let Some(range) = change.range else {
+                // If change.range is None, change.text represents the whole file
+                self.source_code = change.text;
+                if incremental {
+                    match parser_pool.parse(&self.source_code, None) {
+                        Some(tree) => self.tree = tree,
+                        None => incremental = false,
+                    }
+                }
+                old_tree = None;
+                continue;
+            };
+
+            let start_byte = utils::pos_to_byte(range.start, &self.source_code);
+            let old_end_byte = utils::pos_to_byte(range.end, &self.source_code);
 
-            if let Some(range) = change.range {
-                let start_byte = utils::pos_to_byte(range.start, &self.source_code);
-                let old_end_byte = utils::pos_to_byte(range.end, &self.source_code);
+            self.source_code
+                .replace_range(start_byte..old_end_byte, &change.text);
 
+            if incremental {
                 let start_position = utils::pos_to_point(range.start);
 
                 let edit = InputEdit {
                     start_byte,
-                    old_end_byte: utils::pos_to_byte(range.end, &self.source_code),
+                    old_end_byte,
                     new_end_byte: start_byte + change.text.len(),
                     start_position,
                     old_end_position: utils::pos_to_point(range.end),
                     new_end_position: utils::calculate_end_point(start_position, &change.text),
                 };
 
-                self.source_code
-                    .replace_range(start_byte..old_end_byte, &change.text);
-
-                text = self.source_code.clone();
-                let tree = &mut self.tree;
-                tree.edit(&edit);
-                old_tree = Some(tree);
-            } else {
-                // If change.range is None, change.text represents the whole file
-                text = change.text.clone();
+                self.tree.edit(&edit);
+                if let Some(old_tree) = &mut old_tree {
+                    old_tree.edit(&edit);
+                }
+                let Some(new_tree) = parser_pool.parse(&self.source_code, Some(&self.tree)) else {
+                    // The remaining changes only edit the text, which is parsed again at the end
+                    incremental = false;
+                    continue;
+                };
+                self.tree = new_tree;
             }
+        }
 
-            self.tree = self.parser.parse(text, old_tree).unwrap();
+        let mut indexed = self.source_code.len() <= max_size;
+        if !incremental || !indexed {
+            let parsed = if indexed {
+                parser_pool.parse(&self.source_code, None)
+            } else {
+                None
+            };
+            indexed = parsed.is_some();
+            self.tree = parsed.unwrap_or_else(|| parser_pool.parse_empty());
         }
+        self.indexed = indexed;
 
         let mut ast_manager = self.ast_manager.lock().unwrap();
         let mut symbol_table_manager = self.symbol_table_manager.lock().unwrap();
 
-        ast_manager.update(&self.source_code, self.tree.clone());
+        // Ranges of earlier changes would be shifted by later ones, so only the final trees are
+        // compared
+        match old_tree {
+            Some(old_tree) if incremental && indexed => ast_manager.update_incremental(
+                &self.source_code,
+                self.tree.clone(),
+                old_tree.changed_ranges(&self.tree).collect(),
+            ),
+            _ => ast_manager.update(&self.source_code, self.tree.clone()),
+        }
         symbol_table_manager.update(ast_manager.get_ast());
+        // Comments aren't part of the AST, so they are attached again after every update
+        symbol_table_manager.attach_comments(&collect_comments(&self.tree, &self.source_code));
 
         debug!("\nAST:\n{}", ast_manager);
         debug!("\nSymbol Table:\n{}", symbol_table_manager);
     }
 
-    pub fn get_import_paths(&self) -> Vec<Result<(workspace::Import, PathBuf), lsp_types::Range>> {
+    /// Resolves the imports of the file like a compiler would: local imports are first looked up
+    /// relative to the file, then in the library paths in order. `include_paths` come before the
+    /// library paths of the language.
+    pub fn get_import_paths(&self, include_paths: &[PathBuf]) -> Vec<ImportPath> {
         let ast = self.ast_manager.lock().unwrap();
         let visit = ast.visit_root();
         let nodes = visit.get_descendants();
 
+        let mut library_roots = include_paths.to_vec();
+        library_roots.extend(get_library_roots());
+
         nodes
             .iter()
-            .filter_map(|node| match node.get().import {
-                Import::Local => {
-                    let file_name = node.get().content.clone();
-                    let mut curr_path = self.uri.to_file_path().unwrap();
-                    curr_path.pop(); // Get dir
-
-                    curr_path.push(file_name);
-
-                    if curr_path.exists() {
-                        Some(Ok((workspace::Import::Local, curr_path)))
-                    } else {
-                        Some(Err(node.get().range))
-                    }
-                }
-                Import::Library => {
-                    let lib_paths = &LanguageDefinition::get().language.library_paths;
-                    let file_name = &node.get().content;
-
-                    if let Some(path) = lib_paths.env_variables.iter().find_map(|var| {
-                        if let Ok(existing_var) = env::var(var) {
-                            if let Ok(mut path) = existing_var.parse::<PathBuf>() {
-                                path.push(file_name);
-                                if path.exists() {
-                                    return Some(path);
-                                }
-                            }
-                        }
-                        None
-                    }) {
-                        return Some(Ok((workspace::Import::Library, path)));
-                    }
-
-                    if cfg!(target_os = "windows") {
-                        if let Some(path) = utils::find_lib(&lib_paths.windows, file_name) {
-                            return Some(Ok((workspace::Import::Library, path)));
-                        }
-                    } else if cfg!(target_os = "macos") {
-                        if let Some(path) = utils::find_lib(&lib_paths.macos, file_name) {
-                            return Some(Ok((workspace::Import::Library, path)));
-                        }
-                    } else if cfg!(target_os = "linux") {
-                        if let Some(path) = utils::find_lib(&lib_paths.linux, file_name) {
-                            return Some(Ok((workspace::Import::Library, path)));
-                        }
-                    } else {
-                        error!("Unsupported platform for imports, all file imports will fail.");
-                    }
+            .filter_map(|node| {
+                let (import_type, roots) = match node.get().import {
+                    Import::Local => (
+                        workspace::Import::Local,
+                        get_local_roots(&self.uri, &library_roots),
+                    ),
+                    Import::Library => (workspace::Import::Library, library_roots.clone()),
+                    Import::None => return None,
+                };
 
-                    Some(Err(node.get().range))
-                }
-                Import::None => None,
+                let range = node.get().range;
+                let file_name = &node.get().content;
+                Some(
+                    utils::resolve_include(&roots, file_name)
+                        .map(|include| (import_type, include, range))
+                        .ok_or_else(|| (range, file_name.clone())),
+                )
             })
             .collect()
     }
 
     pub fn get_quick_diagnostics(&self) -> Vec<Diagnostic> {
-        diagnostics::get_quick(&self.uri, &self.ast_manager, &self.symbol_table_manager)
+        diagnostics::get_quick(
+            &self.uri,
+            &self.tree,
+            &self.source_code,
+            &self.ast_manager,
+            &self.symbol_table_manager,
+        )
     }
 
     pub fn get_full_diagnostics(&self) -> Vec<Diagnostic> {
-        diagnostics::get_full(&self.uri, &self.ast_manager, &self.symbol_table_manager)
+        diagnostics::get_full(
+            &self.uri,
+            &self.tree,
+            &self.source_code,
+            &self.ast_manager,
+            &self.symbol_table_manager,
+        )
     }
 
     pub fn get_completion_list(
@@ -191,8 +251,29 @@ impl File {
         )
     }
 
-    pub fn get_import_completion_list(&self) -> Vec<CompletionItem> {
-        completion::get_imported_list(&self.uri, &self.symbol_table_manager)
+    /// Part of the name being written before the position.
+    pub fn get_word_prefix(&self, position: Position) -> &str {
+        utils::get_word_before(
+            &self.source_code,
+            utils::pos_to_byte(position, &self.source_code),
+        )
+    }
+
+    pub fn get_directive_completion_list(&self, position: Position) -> Option<Vec<CompletionItem>> {
+        let line_prefix = utils::get_line_before(
+            &self.source_code,
+            utils::pos_to_byte(position, &self.source_code),
+        );
+
+        completion::directive_list(&LanguageDefinition::get().directives, line_prefix, position)
+    }
+
+    pub fn get_completion_context(&self, position: Position) -> Option<RuleContext> {
+        completion::get_context_rule(position, &self.ast_manager)
+    }
+
+    pub fn get_import_completion_list(&self, context: Option<&RuleContext>) -> Vec<CompletionItem> {
+        completion::get_imported_list(&self.uri, &self.symbol_table_manager, context)
     }
 
     pub fn get_symbol_id_at_pos(&self, position: Position) -> Option<SymbolId> {
@@ -203,12 +284,264 @@ impl File {
         node.get().linked_symbol.clone()
     }
 
-    pub fn get_semantic_tokens(&self) -> SemanticTokensResult {
+    /// Name of the usage at the position if it isn't linked to any symbol.
+    pub fn get_unlinked_usage_name(&self, position: Position) -> Option<String> {
+        let ast_query = self.ast_manager.lock().unwrap();
+        let root_visit = ast_query.visit_root();
+        let node = root_visit.get_node_at_position(position)?;
+        let node = node.get();
+
+        (node.symbol == Symbol::Usage && node.linked_symbol.is_none()).then(|| node.content.clone())
+    }
+
+    /// Whether hovering the position can show anything, see `hover::is_hoverable`.
+    pub fn is_hoverable(&self, position: Position) -> bool {
+        let node_kind = self.get_syntax_node(position).map(|node| node.kind());
+
+        hover::is_hoverable(
+            &self.source_code,
+            position,
+            node_kind,
+            &LanguageDefinition::get().comment_nodes,
+        )
+    }
+
+    /// Describes the base type containing the position, if any.
+    pub fn get_base_type_info(&self, position: Position) -> Option<HoverContents> {
+        let base_types = &LanguageDefinition::get().base_types;
+        let point = utils::pos_to_point(position);
+        let mut node = self
+            .tree
+            .root_node()
+            .descendant_for_point_range(point, point)?;
+
+        loop {
+            if let Some(base_type) = base_types.iter().find(|t| t.node_name == node.kind()) {
+                let width = base_type
+                    .width_field
+                    .as_ref()
+                    .and_then(|field| node.child_by_field_name(field))
+                    .map(|width| utils::get_node_text(&width, &self.source_code));
+
+                return Some(hover::get_base_type_info(base_type, width.as_deref()));
+            }
+            node = node.parent()?;
+        }
+    }
+
+    /// Describes the keyword or the undefined usage at the position, if any.
+    pub fn get_fallback_hover_info(&self, position: Position) -> Option<HoverContents> {
+        let syntax_node = self.get_syntax_node(position)?;
+        let word = utils::get_node_text(&syntax_node, &self.source_code);
+        if LanguageDefinition::get_keywords().contains(&word) {
+            return Some(hover::get_fallback_info(&word, true));
+        }
+
+        let ast_query = self.ast_manager.lock().unwrap();
+        let root_visit = ast_query.visit_root();
+        let node = root_visit.get_node_at_position(position)?;
+        let node = node.get();
+        if node.symbol == Symbol::Usage && node.linked_symbol.is_none() {
+            return Some(hover::get_fallback_info(&node.content, false));
+        }
+
+        None
+    }
+
+    /// Line of the source code, if the file has that many lines.
+    pub fn get_line(&self, line: u32) -> Option<&str> {
+        self.source_code.lines().nth(line as usize)
+    }
+
+    /// Innermost syntax node at the position.
+    pub fn get_syntax_node(&self, position: Position) -> Option<tree_sitter::Node<'_>> {
+        let point = utils::pos_to_point(position);
+        self.tree
+            .root_node()
+            .descendant_for_point_range(point, point)
+    }
+
+    pub fn get_calls(&self, range: lsp_types::Range) -> Vec<Call> {
+        inlay_hints::get_calls(range, &self.ast_manager)
+    }
+
+    /// Innermost call containing the position, with the index of the argument it is in.
+    pub fn get_call_at(&self, position: Position) -> Option<(Call, u32)> {
+        let call = self
+            .get_calls(lsp_types::Range::new(position, position))
+            .into_iter()
+            .max_by_key(|call| call.range.start)?;
+
+        let start = utils::pos_to_byte(call.range.start, &self.source_code);
+        let end = utils::pos_to_byte(position, &self.source_code);
+        let call_text = self.source_code.get(start..end)?;
+
+        Some((call, signature_help::active_parameter(call_text)))
+    }
+
+    pub fn get_folding_ranges(&self) -> Vec<lsp_types::FoldingRange> {
+        let scope_ranges = {
+            let symbol_table_manager = self.symbol_table_manager.lock().unwrap();
+            let mut scope_ranges = vec![];
+            let mut scopes = vec![symbol_table_manager.get_symbols_at_root().1];
+            while let Some(scope_id) = scopes.pop() {
+                scope_ranges.extend(symbol_table_manager.get_scope_range(scope_id));
+                scopes.extend(symbol_table_manager.get_subscopes(scope_id));
+            }
+            scope_ranges
+        };
+
+        let mut folds = folding::scope_folds(&scope_ranges, &self.source_code);
+        folds.extend(folding::comment_folds(&collect_comments(
+            &self.tree,
+            &self.source_code,
+        )));
+        folds.sort_by_key(|fold| (fold.start_line, fold.end_line));
+        folds
+    }
+
+    pub fn get_document_symbols(&self, hierarchical: bool) -> DocumentSymbolResponse {
+        if hierarchical {
+            DocumentSymbolResponse::Nested(document_symbols::get_nested(&self.symbol_table_manager))
+        } else {
+            DocumentSymbolResponse::Flat(document_symbols::get_flat(
+                &self.uri,
+                &self.symbol_table_manager,
+            ))
+        }
+    }
+
+    pub fn get_semantic_tokens(&self, extra_tokens: Vec<ColorData>) -> SemanticTokensResult {
         semantic_tokens::get_tokens(
             &self.ast_manager,
             &self.symbol_table_manager,
             &self.tree,
             &self.source_code,
+            extra_tokens,
         )
     }
 }
+
+/// Roots of the local imports: the directory of the file, then the library paths. Documents that
+/// aren't files, like `untitled:` ones, only have the library paths.
+fn get_local_roots(uri: &Url, library_roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| path.parent().map(PathBuf::from))
+        .into_iter()
+        .collect();
+    roots.extend(library_roots.iter().cloned());
+
+    roots
+}
+
+/// Library paths from the environment variables first, then the platform's default paths.
+fn get_library_roots() -> Vec<PathBuf> {
+    let lib_paths = &LanguageDefinition::get().language.library_paths;
+
+    let mut roots: Vec<PathBuf> = lib_paths
+        .env_variables
+        .iter()
+        .filter_map(|var| env::var(var).ok().map(PathBuf::from))
+        .collect();
+
+    if cfg!(target_os = "windows") {
+        roots.extend(lib_paths.windows.iter().cloned());
+    } else if cfg!(target_os = "macos") {
+        roots.extend(lib_paths.macos.iter().cloned());
+    } else if cfg!(target_os = "linux") {
+        roots.extend(lib_paths.linux.iter().cloned());
+    } else {
+        error!("Unsupported platform for imports, all library imports will fail.");
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tower_lsp::lsp_types::{TextDocumentContentChangeEvent, Url};
+    use tree_sitter::{Node, Point, Tree};
+
+    use super::{get_local_roots, File};
+    use crate::language_def;
+    use crate::project::metadata::test_utils::{range, test_url};
+    use crate::project::parser_pool::ParserPool;
+    use crate::project::test_language;
+
+    fn change(line: u32, start: u32, end: u32, text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(range(line, start, end)),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    fn nodes(tree: &Tree) -> Vec<(String, usize, usize, Point, Point)> {
+        fn collect(node: Node, nodes: &mut Vec<(String, usize, usize, Point, Point)>) {
+            nodes.push((
+                node.kind().to_string(),
+                node.start_byte(),
+                node.end_byte(),
+                node.start_position(),
+                node.end_position(),
+            ));
+            for child in node.children(&mut node.walk()) {
+                collect(child, nodes);
+            }
+        }
+
+        let mut nodes = vec![];
+        collect(tree.root_node(), &mut nodes);
+        nodes
+    }
+
+    #[test]
+    fn test_incremental_update_matches_parse() {
+        language_def::load_test_definition();
+        let parser_pool = ParserPool::new(test_language::language());
+        let mut file = File::new(test_url(), "control c\n  apply", &parser_pool, usize::MAX);
+
+        file.update(
+            vec![
+                // Text ending with a line break, then one spanning several lines
+                change(0, 8, 8, "ingress\n"),
+                change(2, 2, 7, "x = 1;\n  y = 2;"),
+                // Removing a line break
+                change(0, 15, 16, " "),
+            ],
+            &parser_pool,
+            usize::MAX,
+        );
+
+        assert_eq!(file.source_code, "control ingress c\n  x = 1;\n  y = 2;");
+        assert!(file.is_indexed());
+        assert_eq!(
+            nodes(&file.tree),
+            nodes(&parser_pool.parse(&file.source_code, None).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_untitled_document_roots() {
+        let library_roots = vec![PathBuf::from("/usr/share/p4c/p4include")];
+
+        assert_eq!(
+            get_local_roots(
+                &Url::parse("file:///home/user/main.p4").unwrap(),
+                &library_roots
+            ),
+            vec![
+                PathBuf::from("/home/user"),
+                PathBuf::from("/usr/share/p4c/p4include")
+            ]
+        );
+        assert_eq!(
+            get_local_roots(&Url::parse("untitled:Untitled-1").unwrap(), &library_roots),
+            library_roots
+        );
+    }
+}
@@ -1,4 +1,4 @@
 mod rules_translator;
 mod tree;
 
-pub use tree::{Ast, Node, NodeKind, Translator, VisitNode, Visitable};
+pub use tree::{Ast, Node, NodeKind, TranslationError, Translator, VisitNode, Visitable};
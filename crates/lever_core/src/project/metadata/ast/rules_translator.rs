@@ -1,23 +1,39 @@
+use std::sync::Once;
+
 use indextree::{Arena, NodeId};
+use tower_lsp::lsp_types::{Position, Range};
 
-use super::{tree::Translator, Ast, Node, NodeKind};
+use super::{tree::Translator, Ast, Node, NodeKind, TranslationError, Visitable};
 use crate::{
     language_def::{
         Child, DirectOrRule, Import, LanguageDefinition, Rule, Symbol, TreesitterNodeQuery,
     },
     lsp_mappings::HighlightType,
+    utils,
 };
 
 pub struct RulesTranslator {
     arena: Arena<Node>,
     language_def: &'static LanguageDefinition,
+    previous: Option<PreviousAst>,
+    errors: Vec<TranslationError>,
+}
+
+// Translation of the file before an edit, whose unchanged top-level nodes can be reused
+struct PreviousAst {
+    arena: Arena<Node>,
+    reusable: Vec<NodeId>,
+    changed_ranges: Vec<tree_sitter::Range>,
+    errors: Vec<TranslationError>,
 }
 
 impl Translator for RulesTranslator {
     fn translate(&mut self, source_code: &str, syntax_tree: tree_sitter::Tree) -> Ast {
         let root_id = self.build(source_code, syntax_tree);
+        let mut errors = std::mem::take(&mut self.errors);
+        errors.append(&mut check_symbols(&mut self.arena, root_id));
 
-        Ast::initialize(self.arena.clone(), root_id)
+        Ast::initialize(self.arena.clone(), root_id).with_errors(errors)
     }
 }
 
@@ -26,6 +42,35 @@ impl RulesTranslator {
         RulesTranslator {
             arena: Arena::new(),
             language_def: LanguageDefinition::get(),
+            previous: None,
+            errors: vec![],
+        }
+    }
+
+    /// Creates a translator copying the top-level nodes of `previous` whose text didn't change
+    /// instead of translating them again. `changed_ranges` are the ranges of the new syntax tree
+    /// whose structure changed, nodes overlapping them are always translated.
+    pub fn with_previous(
+        previous: &Ast,
+        changed_ranges: Vec<tree_sitter::Range>,
+    ) -> RulesTranslator {
+        let root_visit = previous.visit_root();
+        let reusable = root_visit
+            .get_children()
+            .iter()
+            .map(|child| child.get_id())
+            .collect();
+
+        RulesTranslator {
+            arena: Arena::new(),
+            language_def: LanguageDefinition::get(),
+            previous: Some(PreviousAst {
+                arena: previous.get_arena_ref().clone(),
+                reusable,
+                changed_ranges,
+                errors: previous.get_errors().to_vec(),
+            }),
+            errors: vec![],
         }
     }
 
@@ -42,7 +87,11 @@ impl RulesTranslator {
         current_ts_node: &tree_sitter::Node,
     ) -> NodeId {
         let mut cursor = current_ts_node.walk();
-        let children: Vec<tree_sitter::Node> = current_ts_node.children(&mut cursor).collect();
+        let children = kept_children(
+            current_ts_node.children(&mut cursor),
+            |node| node.kind(),
+            &self.language_def.ignored_nodes,
+        );
 
         let current_node_id = self.new_node(
             source_code,
@@ -53,7 +102,7 @@ impl RulesTranslator {
             None,
         );
         // TODO: has_error vs is_error
-        for error_ts_node in children.iter().filter(|node| node.is_error()) {
+        for (_, error_ts_node) in children.iter().filter(|(_, node)| node.is_error()) {
             current_node_id.append(
                 self.new_error_node(source_code, error_ts_node, None),
                 &mut self.arena,
@@ -61,11 +110,23 @@ impl RulesTranslator {
         }
 
         for child in &current_rule.children {
-            self.query_parse_child(source_code, &children, child, current_node_id);
+            self.query_parse_child(
+                source_code,
+                current_ts_node,
+                &children,
+                child,
+                current_node_id,
+            );
         }
 
         for child in &LanguageDefinition::get().global_ast_rules {
-            self.query_parse_child(source_code, &children, child, current_node_id);
+            self.query_parse_child(
+                source_code,
+                current_ts_node,
+                &children,
+                child,
+                current_node_id,
+            );
         }
 
         current_node_id
@@ -74,13 +135,15 @@ impl RulesTranslator {
     fn query_parse_child(
         &mut self,
         source_code: &str,
-        children: &[tree_sitter::Node],
+        current_ts_node: &tree_sitter::Node,
+        children: &[(usize, tree_sitter::Node<'_>)],
         child: &Child,
         current_node_id: NodeId,
     ) {
         let (query, node_or_rule) = (&child.query, &child.rule);
 
-        for (i, ts_node) in children.iter().enumerate() {
+        let mut matches: Vec<(tree_sitter::Node, tree_sitter::Node)> = vec![];
+        for &(i, ref ts_node) in children {
             let target_node = if let TreesitterNodeQuery::Path(path) = query {
                 if path.is_empty() {
                     continue;
@@ -88,7 +151,7 @@ impl RulesTranslator {
 
                 let mut current_ts_node = *ts_node;
                 if !match &path[0] {
-                    TreesitterNodeQuery::Path(_) => unimplemented!(), // TODO
+                    TreesitterNodeQuery::Path(_) => nested_path(),
                     TreesitterNodeQuery::Kind(kind) => current_ts_node.kind() == kind,
                     TreesitterNodeQuery::Field(name) => {
                         current_ts_node
@@ -108,7 +171,7 @@ impl RulesTranslator {
                         .enumerate()
                         .filter(|node| node.1.is_named())
                         .find(|(i, ts_node)| match element {
-                            TreesitterNodeQuery::Path(_) => unimplemented!(),
+                            TreesitterNodeQuery::Path(_) => nested_path(),
                             TreesitterNodeQuery::Kind(kind) => ts_node.kind() == kind,
                             TreesitterNodeQuery::Field(name) => {
                                 ts_node.parent().unwrap().field_name_for_child(*i as u32)
@@ -132,39 +195,141 @@ impl RulesTranslator {
                 }
                 TreesitterNodeQuery::Path(_) => true,
             } {
-                match node_or_rule {
-                    DirectOrRule::Direct(node_kind) => {
-                        if ts_node.has_error() {
-                            current_node_id.append(
-                                self.new_error_node(source_code, ts_node, None),
-                                &mut self.arena,
-                            );
-                        }
+                matches.push((*ts_node, target_node));
+            }
+        }
 
+        let Some(matches) = child.multiplicity.select(matches) else {
+            let name = match node_or_rule {
+                DirectOrRule::Direct(name) | DirectOrRule::Rule(name) => name,
+            };
+            // Point at the end of the parent rather than covering all of it
+            let end = utils::point_to_pos(current_ts_node.end_position());
+            self.errors.push(TranslationError {
+                range: Range::new(end, end),
+                message: format!("Missing {name}."),
+            });
+            return;
+        };
+
+        for (ts_node, target_node) in matches {
+            match node_or_rule {
+                DirectOrRule::Direct(node_kind) => {
+                    if ts_node.has_error() {
                         current_node_id.append(
-                            self.new_node(
-                                source_code,
-                                NodeKind::Node(node_kind.clone()),
-                                &target_node,
-                                Symbol::None,
-                                Import::None,
-                                child.highlight_type.clone(),
-                            ),
-                            &mut self.arena,
-                        );
-                    }
-                    DirectOrRule::Rule(name) => {
-                        let rule = self.language_def.rule_with_name(name).unwrap().clone();
-                        current_node_id.append(
-                            self.parse(&rule, source_code, &target_node),
+                            self.new_error_node(source_code, &ts_node, None),
                             &mut self.arena,
                         );
                     }
+
+                    current_node_id.append(
+                        self.new_node(
+                            source_code,
+                            NodeKind::Node(node_kind.clone()),
+                            &target_node,
+                            Symbol::None,
+                            Import::None,
+                            child.highlight_type.clone(),
+                        ),
+                        &mut self.arena,
+                    );
+                }
+                DirectOrRule::Rule(name) => {
+                    let Some(rule) = self.language_def.rule_with_name(name) else {
+                        self.errors.push(TranslationError {
+                            range: utils::ts_range_to_lsp_range(target_node.range()),
+                            message: format!("No rule named {name} to translate this node."),
+                        });
+                        continue;
+                    };
+
+                    // Only top-level nodes are reused
+                    let reused_node_id = if current_ts_node.parent().is_none() {
+                        self.reuse_node(rule, source_code, &target_node)
+                    } else {
+                        None
+                    };
+
+                    let node_id = reused_node_id
+                        .unwrap_or_else(|| self.parse(&rule.clone(), source_code, &target_node));
+                    current_node_id.append(node_id, &mut self.arena);
                 }
             }
         }
     }
 
+    fn reuse_node(
+        &mut self,
+        rule: &Rule,
+        source_code: &str,
+        syntax_node: &tree_sitter::Node,
+    ) -> Option<NodeId> {
+        let mut previous = self.previous.take()?;
+
+        let overlaps_change = previous.changed_ranges.iter().any(|range| {
+            range.start_byte < syntax_node.end_byte() && syntax_node.start_byte() < range.end_byte
+        });
+        let kind = NodeKind::Node(rule.node_name.clone());
+        let content = utils::get_node_text(syntax_node, source_code);
+
+        let node_id = if overlaps_change {
+            None
+        } else {
+            previous
+                .reusable
+                .iter()
+                .position(|id| {
+                    let node = previous.arena.get(*id).unwrap().get();
+                    node.kind == kind && node.content == content
+                })
+                .map(|index| {
+                    let old_id = previous.reusable.remove(index);
+                    let old_range = previous.arena.get(old_id).unwrap().get().range;
+                    let new_start = utils::point_to_pos(syntax_node.start_position());
+
+                    for error in &previous.errors {
+                        if error.range.start >= old_range.start && error.range.end <= old_range.end
+                        {
+                            self.errors.push(TranslationError {
+                                range: Range::new(
+                                    shift_position(error.range.start, old_range.start, new_start),
+                                    shift_position(error.range.end, old_range.start, new_start),
+                                ),
+                                message: error.message.clone(),
+                            });
+                        }
+                    }
+
+                    self.copy_subtree(&previous.arena, old_id, old_range.start, new_start)
+                })
+        };
+
+        self.previous = Some(previous);
+        node_id
+    }
+
+    // Symbols are linked again when the symbol table is rebuilt, so links aren't copied
+    fn copy_subtree(
+        &mut self,
+        arena: &Arena<Node>,
+        node_id: NodeId,
+        old_start: Position,
+        new_start: Position,
+    ) -> NodeId {
+        let mut node = arena.get(node_id).unwrap().get().clone();
+        node.range.start = shift_position(node.range.start, old_start, new_start);
+        node.range.end = shift_position(node.range.end, old_start, new_start);
+        node.linked_symbol = None;
+
+        let new_node_id = self.arena.new_node(node);
+        for child_id in node_id.children(arena) {
+            let new_child_id = self.copy_subtree(arena, child_id, old_start, new_start);
+            new_node_id.append(new_child_id, &mut self.arena);
+        }
+
+        new_node_id
+    }
+
     fn new_node(
         &mut self,
         source_code: &str,
@@ -200,3 +365,199 @@ impl RulesTranslator {
         ))
     }
 }
+
+/// Reports the symbol declarations missing the nodes their rule names, and removes what's missing
+/// from them so that building the symbol table doesn't depend on these nodes.
+fn check_symbols(arena: &mut Arena<Node>, root_id: NodeId) -> Vec<TranslationError> {
+    let mut errors: Vec<TranslationError> = vec![];
+
+    let ids: Vec<NodeId> = root_id.descendants(arena).collect();
+    for id in ids {
+        let node = arena.get(id).unwrap().get();
+        let Symbol::Init {
+            kind,
+            name_node,
+            type_node,
+        } = &node.symbol
+        else {
+            continue;
+        };
+
+        let has_child = |child_kind: &str| {
+            id.children(arena).any(|child_id| {
+                arena.get(child_id).unwrap().get().kind == NodeKind::Node(child_kind.to_string())
+            })
+        };
+        let missing_name = !has_child(name_node);
+        let missing_type = type_node
+            .as_ref()
+            .is_some_and(|type_node| !has_child(type_node));
+        if !missing_name && !missing_type {
+            continue;
+        }
+
+        let message = if missing_name {
+            format!("Missing name of {kind}.")
+        } else {
+            format!("Missing type of {kind}.")
+        };
+        errors.push(TranslationError {
+            range: node.range,
+            message,
+        });
+
+        let node = arena.get_mut(id).unwrap().get_mut();
+        if missing_name {
+            node.symbol = Symbol::None;
+        } else if let Symbol::Init { type_node, .. } = &mut node.symbol {
+            *type_node = None;
+        }
+    }
+
+    errors
+}
+
+// Path elements that are paths themselves, reported when validating the definition, match nothing
+fn nested_path() -> bool {
+    static WARNING: Once = Once::new();
+    WARNING.call_once(|| warn!("Nested paths are not supported, they never match."));
+
+    false
+}
+
+/// Removes the children whose kind is ignored, keeping the index of the others in their parent so
+/// that their field names can still be looked up.
+fn kept_children<T>(
+    children: impl Iterator<Item = T>,
+    kind: impl Fn(&T) -> &str,
+    ignored_nodes: &[String],
+) -> Vec<(usize, T)> {
+    children
+        .enumerate()
+        .filter(|(_, child)| !ignored_nodes.iter().any(|ignored| ignored == kind(child)))
+        .collect()
+}
+
+/// Moves a position of a subtree starting at `old_start` so that the subtree starts at
+/// `new_start`. Only the positions on the first line of the subtree change column.
+fn shift_position(position: Position, old_start: Position, new_start: Position) -> Position {
+    if position.line == old_start.line {
+        Position::new(
+            new_start.line,
+            new_start.character + position.character - old_start.character,
+        )
+    } else {
+        Position::new(
+            position.line - old_start.line + new_start.line,
+            position.character,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indextree::Arena;
+    use tower_lsp::lsp_types::Position;
+
+    use super::TranslationError;
+    use super::{check_symbols, kept_children, shift_position};
+    use crate::language_def::Symbol;
+    use crate::project::metadata::test_utils::{init, node, range};
+
+    #[test]
+    fn test_malformed_declarations() {
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", Symbol::None, range(0, 0, 10)));
+
+        // `const bit<8> = 1;`
+        let nameless_constant = arena.new_node(node(
+            "ConstantDec",
+            "",
+            init("Constant", Some("Type")),
+            range(1, 0, 10),
+        ));
+        nameless_constant.append(
+            arena.new_node(node("Type", "", Symbol::None, range(1, 0, 10))),
+            &mut arena,
+        );
+        root.append(nameless_constant, &mut arena);
+
+        // `const X = 1;`
+        let untyped_constant = arena.new_node(node(
+            "ConstantDec",
+            "",
+            init("Constant", Some("Type")),
+            range(2, 0, 10),
+        ));
+        untyped_constant.append(
+            arena.new_node(node("Name", "", Symbol::None, range(2, 0, 10))),
+            &mut arena,
+        );
+        root.append(untyped_constant, &mut arena);
+
+        // `action drop() {}`
+        let action = arena.new_node(node("ActionDec", "", init("Action", None), range(3, 0, 10)));
+        action.append(
+            arena.new_node(node("Name", "", Symbol::None, range(3, 0, 10))),
+            &mut arena,
+        );
+        root.append(action, &mut arena);
+
+        assert_eq!(
+            check_symbols(&mut arena, root),
+            vec![
+                TranslationError {
+                    range: range(1, 0, 10),
+                    message: String::from("Missing name of Constant."),
+                },
+                TranslationError {
+                    range: range(2, 0, 10),
+                    message: String::from("Missing type of Constant."),
+                },
+            ]
+        );
+        assert_eq!(
+            arena.get(nameless_constant).unwrap().get().symbol,
+            Symbol::None
+        );
+        assert_eq!(
+            arena.get(untyped_constant).unwrap().get().symbol,
+            init("Constant", None)
+        );
+        assert_eq!(
+            arena.get(action).unwrap().get().symbol,
+            init("Action", None)
+        );
+    }
+
+    #[test]
+    fn test_ignored_node_kinds() {
+        // `x = y ;`
+        let children = ["identifier", "=", "identifier", ";"];
+        let ignored_nodes = vec![String::from(";"), String::from(",")];
+
+        assert_eq!(
+            kept_children(children.into_iter(), |kind| kind, &ignored_nodes),
+            vec![(0, "identifier"), (1, "="), (2, "identifier")]
+        );
+        assert_eq!(
+            kept_children([",", "identifier"].into_iter(), |kind| kind, &ignored_nodes),
+            vec![(1, "identifier")]
+        );
+    }
+
+    #[test]
+    fn test_shift_position() {
+        let old_start = Position::new(4, 2);
+        let new_start = Position::new(6, 8);
+
+        assert_eq!(
+            shift_position(Position::new(4, 10), old_start, new_start),
+            Position::new(6, 16)
+        );
+        assert_eq!(
+            shift_position(Position::new(5, 3), old_start, new_start),
+            Position::new(7, 3)
+        );
+    }
+}
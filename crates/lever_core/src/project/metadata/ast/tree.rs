@@ -54,6 +54,15 @@ impl NodeKind {
     pub fn is_scope_node(&self) -> bool {
         language_def::LanguageDefinition::get_scope_nodes().contains(self)
     }
+
+    pub fn is_annotation_node(&self) -> bool {
+        match self {
+            NodeKind::Node(name) => language_def::LanguageDefinition::get()
+                .annotation_nodes
+                .contains(name),
+            NodeKind::Error(_) => false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -95,11 +104,11 @@ impl Node {
 pub trait Visitable {
     fn get(&self) -> &Node;
     fn get_id(&self) -> NodeId;
-    fn get_children(&self) -> Vec<VisitNode>;
-    fn get_descendants(&self) -> Vec<VisitNode>;
-    fn get_child_of_kind(&self, kind: NodeKind) -> Option<VisitNode>;
-    fn get_subscopes(&self) -> Vec<VisitNode>;
-    fn get_node_at_position(&self, position: Position) -> Option<VisitNode>;
+    fn get_children(&self) -> Vec<VisitNode<'_>>;
+    fn get_descendants(&self) -> Vec<VisitNode<'_>>;
+    fn get_child_of_kind(&self, kind: NodeKind) -> Option<VisitNode<'_>>;
+    fn get_subscopes(&self) -> Vec<VisitNode<'_>>;
+    fn get_node_at_position(&self, position: Position) -> Option<VisitNode<'_>>;
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -119,21 +128,21 @@ impl Visitable for VisitNode<'_> {
         self.arena.get(self.id).unwrap().get()
     }
 
-    fn get_children(&self) -> Vec<VisitNode> {
+    fn get_children(&self) -> Vec<VisitNode<'_>> {
         self.id
             .children(self.arena)
             .map(|id| VisitNode::new(self.arena, id))
             .collect::<Vec<VisitNode>>()
     }
 
-    fn get_descendants(&self) -> Vec<VisitNode> {
+    fn get_descendants(&self) -> Vec<VisitNode<'_>> {
         self.id
             .descendants(self.arena)
             .map(|id| VisitNode::new(self.arena, id))
             .collect::<Vec<VisitNode>>()
     }
 
-    fn get_child_of_kind(&self, kind: NodeKind) -> Option<VisitNode> {
+    fn get_child_of_kind(&self, kind: NodeKind) -> Option<VisitNode<'_>> {
         let id = self
             .id
             .children(self.arena)
@@ -142,14 +151,14 @@ impl Visitable for VisitNode<'_> {
         Some(VisitNode::new(self.arena, id))
     }
 
-    fn get_subscopes(&self) -> Vec<VisitNode> {
+    fn get_subscopes(&self) -> Vec<VisitNode<'_>> {
         self.get_children()
             .into_iter()
             .filter(|child| child.get().kind.is_scope_node())
             .collect::<Vec<VisitNode>>()
     }
 
-    fn get_node_at_position(&self, position: Position) -> Option<VisitNode> {
+    fn get_node_at_position(&self, position: Position) -> Option<VisitNode<'_>> {
         let mut child_id = self.id;
 
         loop {
@@ -174,10 +183,18 @@ pub trait Translator {
     fn translate(&mut self, source_code: &str, syntax_tree: tree_sitter::Tree) -> Ast;
 }
 
+// Problem found while translating a syntax tree that doesn't fit the rules
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TranslationError {
+    pub range: Range,
+    pub message: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct Ast {
     arena: Arena<Node>,
     root_id: NodeId,
+    errors: Vec<TranslationError>,
 }
 
 impl fmt::Display for Ast {
@@ -188,7 +205,20 @@ impl fmt::Display for Ast {
 
 impl Ast {
     pub const fn initialize(arena: Arena<Node>, root_id: NodeId) -> Ast {
-        Ast { arena, root_id }
+        Ast {
+            arena,
+            root_id,
+            errors: vec![],
+        }
+    }
+
+    pub fn with_errors(mut self, errors: Vec<TranslationError>) -> Ast {
+        self.errors = errors;
+        self
+    }
+
+    pub fn get_errors(&self) -> &[TranslationError] {
+        &self.errors
     }
 
     pub fn new(source_code: &str, syntax_tree: tree_sitter::Tree) -> Ast {
@@ -196,6 +226,18 @@ impl Ast {
         translator.translate(source_code, syntax_tree)
     }
 
+    /// Translates an edited syntax tree, reusing the unchanged top-level nodes of `previous`.
+    pub fn new_incremental(
+        source_code: &str,
+        syntax_tree: tree_sitter::Tree,
+        previous: &Ast,
+        changed_ranges: Vec<tree_sitter::Range>,
+    ) -> Ast {
+        let translator: &mut dyn Translator =
+            &mut RulesTranslator::with_previous(previous, changed_ranges);
+        translator.translate(source_code, syntax_tree)
+    }
+
     pub fn link_symbol(&mut self, symbol_id: SymbolId, range: Range) {
         let node_id = self.get_node_at_position(range.start);
         let node = self.arena.get_mut(node_id).unwrap().get_mut();
@@ -219,7 +261,7 @@ impl Ast {
         }
     }
 
-    pub const fn visit_root(&self) -> VisitNode {
+    pub const fn visit_root(&self) -> VisitNode<'_> {
         VisitNode::new(&self.arena, self.root_id)
     }
 
@@ -233,6 +275,10 @@ impl Ast {
         &mut self.arena
     }
 
+    pub const fn get_arena_ref(&self) -> &Arena<Node> {
+        &self.arena
+    }
+
     fn _get_debug_tree(&self, node_id: NodeId, indent: &str, last: bool, result: &mut String) {
         let node = self.arena.get(node_id).unwrap().get();
         let line = format!(
@@ -2,17 +2,24 @@ use core::fmt;
 
 use tower_lsp::lsp_types::Range;
 
-use super::ast::VisitNode;
+use super::ast::{TranslationError, VisitNode};
 
 use super::{Ast, SymbolId};
 
 pub trait AstEditor {
     fn update(&mut self, content: &str, syntax_tree: tree_sitter::Tree);
+    fn update_incremental(
+        &mut self,
+        content: &str,
+        syntax_tree: tree_sitter::Tree,
+        changed_ranges: Vec<tree_sitter::Range>,
+    );
     fn link_symbol(&mut self, symbol_id: SymbolId, range: Range);
 }
 
 pub trait AstQuery {
-    fn visit_root(&self) -> VisitNode;
+    fn visit_root(&self) -> VisitNode<'_>;
+    fn get_translation_errors(&self) -> Vec<TranslationError>;
 }
 
 #[derive(Debug, Clone)]
@@ -38,9 +45,13 @@ impl fmt::Display for AstManager {
 }
 
 impl AstQuery for AstManager {
-    fn visit_root(&self) -> VisitNode {
+    fn visit_root(&self) -> VisitNode<'_> {
         self.ast.visit_root()
     }
+
+    fn get_translation_errors(&self) -> Vec<TranslationError> {
+        self.ast.get_errors().to_vec()
+    }
 }
 
 impl AstEditor for AstManager {
@@ -48,6 +59,25 @@ impl AstEditor for AstManager {
         *self = AstManager::new(content, syntax_tree);
     }
 
+    fn update_incremental(
+        &mut self,
+        content: &str,
+        syntax_tree: tree_sitter::Tree,
+        changed_ranges: Vec<tree_sitter::Range>,
+    ) {
+        let changed_bytes: usize = changed_ranges
+            .iter()
+            .map(|range| range.end_byte - range.start_byte)
+            .sum();
+
+        // Reusing nodes isn't worth it when most of the file changed
+        if changed_bytes * 2 > content.len() {
+            self.update(content, syntax_tree);
+        } else {
+            self.ast = Ast::new_incremental(content, syntax_tree, &self.ast, changed_ranges);
+        }
+    }
+
     fn link_symbol(&mut self, symbol_id: SymbolId, range: Range) {
         self.ast.link_symbol(symbol_id, range);
     }
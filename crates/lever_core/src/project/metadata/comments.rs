@@ -0,0 +1,121 @@
+use tree_sitter::Tree;
+
+use crate::language_def::LanguageDefinition;
+use crate::utils;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub text: String,
+}
+
+/// Collects the comment nodes of the syntax tree, in source order.
+pub fn collect_comments(tree: &Tree, source_code: &str) -> Vec<Comment> {
+    let comment_nodes = &LanguageDefinition::get().comment_nodes;
+    let mut comments: Vec<Comment> = vec![];
+    if comment_nodes.is_empty() {
+        return comments;
+    }
+
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if comment_nodes.iter().any(|kind| kind == node.kind()) {
+            comments.push(Comment {
+                start_line: node.start_position().row as u32,
+                end_line: node.end_position().row as u32,
+                text: utils::get_node_text(&node, source_code),
+            });
+        } else if cursor.goto_first_child() {
+            continue;
+        }
+
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return comments;
+            }
+        }
+    }
+}
+
+/// Returns the block of comments directly above `line`, without the comment markers.
+pub fn find_doc_comment(comments: &[Comment], line: u32) -> Option<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut expected_end = line.checked_sub(1)?;
+
+    while let Some(comment) = comments
+        .iter()
+        .rev()
+        .find(|comment| comment.end_line == expected_end)
+    {
+        for text_line in comment.text.lines().rev() {
+            lines.push(strip_markers(text_line));
+        }
+
+        let Some(previous_line) = comment.start_line.checked_sub(1) else {
+            break;
+        };
+        expected_end = previous_line;
+    }
+
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n").trim().to_string())
+}
+
+fn strip_markers(line: &str) -> String {
+    let line = line.trim();
+    let line = line.strip_suffix("*/").unwrap_or(line);
+    line.trim_start_matches('/')
+        .trim_start_matches('*')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_doc_comment, Comment};
+
+    fn comment(start_line: u32, end_line: u32, text: &str) -> Comment {
+        Comment {
+            start_line,
+            end_line,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_doc_comment_survives_edit_elsewhere() {
+        // 0: /// Ingress pipeline
+        // 1: control ingress() {}
+        // 2: /* Egress
+        // 3:    pipeline */
+        // 4: control egress() {}
+        let comments = vec![
+            comment(0, 0, "/// Ingress pipeline"),
+            comment(2, 3, "/* Egress\n   pipeline */"),
+        ];
+        assert_eq!(
+            find_doc_comment(&comments, 4).as_deref(),
+            Some("Egress\npipeline")
+        );
+
+        // A line is added in the body of `ingress`, moving `egress` and its comment down
+        let comments = vec![
+            comment(0, 0, "/// Ingress pipeline"),
+            comment(3, 4, "/* Egress\n   pipeline */"),
+        ];
+        assert_eq!(
+            find_doc_comment(&comments, 1).as_deref(),
+            Some("Ingress pipeline")
+        );
+        assert_eq!(
+            find_doc_comment(&comments, 5).as_deref(),
+            Some("Egress\npipeline")
+        );
+        assert_eq!(find_doc_comment(&comments, 3), None);
+    }
+}
@@ -1,10 +1,15 @@
 mod ast;
 mod ast_manager;
+mod comments;
 mod st_manager;
 mod symbol;
 mod symbol_table;
+#[cfg(test)]
+pub mod test_utils;
 
 pub use ast::{Ast, Node, NodeKind, Translator, VisitNode, Visitable};
 pub use ast_manager::{AstEditor, AstManager, AstQuery};
+pub use comments::{collect_comments, Comment};
 pub use st_manager::{SymbolTableEditor, SymbolTableManager, SymbolTableQuery};
-pub use symbol::{Symbol, SymbolId, Usage};
+pub use symbol::{Symbol, SymbolId, Usage, Visibility};
+pub use symbol_table::ScopeId;
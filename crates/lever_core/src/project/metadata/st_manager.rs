@@ -1,39 +1,49 @@
 use core::fmt;
 use std::fmt::Debug;
 
+use super::comments::Comment;
 use super::symbol::SymbolId;
 use super::symbol_table::{ScopeId, SymbolTable};
 use super::{Ast, Symbol};
 
 use indextree::NodeId;
-use tower_lsp::lsp_types::{Position, Range};
+use petgraph::prelude::NodeIndex;
+use tower_lsp::lsp_types::{Position, Range, Url};
 
 use super::symbol_table::Actions;
 
 pub trait SymbolTableEditor {
     fn update(&mut self, ast: &mut Ast);
+    fn remove_external_usages(&mut self, file_id: NodeIndex);
+    fn attach_comments(&mut self, comments: &[Comment]);
 }
 
 pub trait SymbolTableQuery {
     fn get_symbols_at_pos(&self, position: Position) -> Vec<Symbol>;
     fn get_symbols_at_root(&self) -> (Vec<Symbol>, NodeId);
     fn get_symbols_in_scope(&self, scope_id: ScopeId) -> Vec<Symbol>;
+    // Unused, the features resolve names through the symbols linked to the usages
+    #[allow(dead_code)]
     fn get_symbol_at_pos(&self, name: String, position: Position) -> Option<&Symbol>;
     fn get_all_symbols(&self) -> Vec<Symbol>;
     fn get_symbol(&self, symbol_id: SymbolId) -> Option<&Symbol>;
     fn get_symbol_mut(&mut self, symbol_id: SymbolId) -> Option<&mut Symbol>;
     fn get_unlinked_symbols(&self) -> Vec<(String, Range)>;
+    fn get_undefined_members(&self) -> Vec<(String, Range)>;
+    fn get_scope_range(&self, scope_id: ScopeId) -> Option<Range>;
+    fn get_subscopes(&self, scope_id: ScopeId) -> Vec<ScopeId>;
 }
 
 #[derive(Debug, Clone)]
 pub struct SymbolTableManager {
     symbol_table: SymbolTable,
+    uri: Url,
 }
 
 impl SymbolTableManager {
-    pub fn new(ast: &mut Ast) -> SymbolTableManager {
-        let symbol_table = SymbolTable::new(ast);
-        SymbolTableManager { symbol_table }
+    pub fn new(ast: &mut Ast, uri: Url) -> SymbolTableManager {
+        let symbol_table = SymbolTable::new(ast, &uri);
+        SymbolTableManager { symbol_table, uri }
     }
 }
 
@@ -78,10 +88,30 @@ impl SymbolTableQuery for SymbolTableManager {
     fn get_unlinked_symbols(&self) -> Vec<(String, Range)> {
         self.symbol_table.get_unlinked_symbols()
     }
+
+    fn get_undefined_members(&self) -> Vec<(String, Range)> {
+        self.symbol_table.get_undefined_members()
+    }
+
+    fn get_scope_range(&self, scope_id: ScopeId) -> Option<Range> {
+        self.symbol_table.get_scope_range(scope_id)
+    }
+
+    fn get_subscopes(&self, scope_id: ScopeId) -> Vec<ScopeId> {
+        self.symbol_table.get_subscopes(scope_id)
+    }
 }
 
 impl SymbolTableEditor for SymbolTableManager {
     fn update(&mut self, ast: &mut Ast) {
-        *self = SymbolTableManager::new(ast);
+        self.symbol_table = SymbolTable::new(ast, &self.uri);
+    }
+
+    fn remove_external_usages(&mut self, file_id: NodeIndex) {
+        self.symbol_table.remove_external_usages(file_id);
+    }
+
+    fn attach_comments(&mut self, comments: &[Comment]) {
+        self.symbol_table.attach_comments(comments);
     }
 }
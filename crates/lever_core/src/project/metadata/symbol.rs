@@ -1,5 +1,5 @@
 use petgraph::prelude::NodeIndex;
-use tower_lsp::lsp_types::Range;
+use tower_lsp::lsp_types::{Location, Range, Url};
 
 use super::symbol_table::ScopeId;
 
@@ -50,6 +50,13 @@ impl Usage {
     }
 }
 
+// Whether including files see the symbol, only top-level declarations are exported
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Visibility {
+    Exported,
+    Local,
+}
+
 #[derive(Debug, Clone)]
 pub struct Symbol {
     pub name: String,
@@ -58,10 +65,18 @@ pub struct Symbol {
     pub def_range: Range,
     pub usages: Vec<Usage>,
     pub field_scope_id: Option<ScopeId>,
+    pub annotations: Vec<String>,
+    // File in which the symbol is defined
+    pub source_uri: Url,
+    pub documentation: Option<String>,
+    pub readonly: bool,
+    pub visibility: Visibility,
+    // Text a macro expands to
+    pub expansion: Option<String>,
 }
 
 impl Symbol {
-    pub const fn new(name: String, kind: String, def_position: Range) -> Symbol {
+    pub const fn new(name: String, kind: String, def_position: Range, source_uri: Url) -> Symbol {
         Symbol {
             name,
             kind,
@@ -69,9 +84,19 @@ impl Symbol {
             def_range: def_position,
             usages: vec![],
             field_scope_id: None,
+            annotations: vec![],
+            source_uri,
+            documentation: None,
+            readonly: false,
+            visibility: Visibility::Local,
+            expansion: None,
         }
     }
 
+    pub fn get_location(&self) -> Location {
+        Location::new(self.source_uri.clone(), self.def_range)
+    }
+
     pub fn add_usage(&mut self, usage: Usage) {
         self.usages.push(usage);
     }
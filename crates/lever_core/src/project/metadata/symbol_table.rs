@@ -4,10 +4,12 @@ use crate::language_def;
 use crate::project::metadata::NodeKind;
 
 use indextree::{Arena, NodeId};
+use petgraph::prelude::NodeIndex;
 use std::fmt;
-use tower_lsp::lsp_types::{Position, Range};
+use tower_lsp::lsp_types::{Position, Range, Url};
 
-use super::{symbol::Usage, Node, Symbol, SymbolId};
+use super::comments::{self, Comment};
+use super::{symbol::Usage, Node, Symbol, SymbolId, Visibility};
 
 pub type ScopeId = NodeId;
 
@@ -16,6 +18,8 @@ pub struct SymbolTable {
     arena: Arena<ScopeSymbolTable>,
     pub root_id: Option<ScopeId>,
     undefined_list: Vec<(String, Range)>,
+    // Members of a local type naming none of its fields
+    undefined_members: Vec<(String, Range)>,
 }
 
 pub trait Actions {
@@ -25,10 +29,19 @@ pub trait Actions {
     fn get_symbols_in_scope_at_pos(&self, position: Position) -> Vec<Symbol>;
     fn get_symbols_at_root(&self) -> Vec<Symbol>;
     fn get_symbols_in_scope(&self, scope_id: ScopeId) -> Vec<Symbol>;
+    #[allow(dead_code)]
     fn get_top_level_symbols(&self) -> Vec<Symbol>;
+    // Unused, the features resolve names through the symbols linked to the usages
+    #[allow(dead_code)]
     fn get_symbol_at_pos(&self, name: String, position: Position) -> Option<&Symbol>;
+    #[allow(dead_code)]
     fn rename_symbol(&mut self, id: usize, new_name: String);
     fn get_unlinked_symbols(&self) -> Vec<(String, Range)>;
+    fn get_undefined_members(&self) -> Vec<(String, Range)>;
+    fn remove_external_usages(&mut self, file_id: NodeIndex);
+    fn get_scope_range(&self, scope_id: ScopeId) -> Option<Range>;
+    fn get_subscopes(&self, scope_id: ScopeId) -> Vec<ScopeId>;
+    fn attach_comments(&mut self, comments: &[Comment]);
 }
 
 impl Actions for SymbolTable {
@@ -112,7 +125,12 @@ impl Actions for SymbolTable {
         for pre_id in scope_id.predecessors(&self.arena) {
             let scope = self.arena.get(pre_id)?.get();
 
-            if let Some(symbol) = scope.symbols.iter().find(|s| s.name == name) {
+            // Innermost scopes first, so that shadowing declarations win
+            if let Some(symbol) = scope
+                .symbols
+                .iter()
+                .find(|s| s.name == name && s.def_range.end < position)
+            {
                 return Some(symbol);
             }
         }
@@ -132,13 +150,49 @@ impl Actions for SymbolTable {
     fn get_unlinked_symbols(&self) -> Vec<(String, Range)> {
         self.undefined_list.clone()
     }
+
+    fn get_undefined_members(&self) -> Vec<(String, Range)> {
+        self.undefined_members.clone()
+    }
+
+    fn get_scope_range(&self, scope_id: ScopeId) -> Option<Range> {
+        Some(self.arena.get(scope_id)?.get().range)
+    }
+
+    fn get_subscopes(&self, scope_id: ScopeId) -> Vec<ScopeId> {
+        scope_id.children(&self.arena).collect()
+    }
+
+    fn attach_comments(&mut self, comments: &[Comment]) {
+        for scope in self.arena.iter_mut() {
+            for symbol in &mut scope.get_mut().symbols {
+                symbol.documentation =
+                    comments::find_doc_comment(comments, symbol.def_range.start.line);
+            }
+        }
+    }
+
+    fn remove_external_usages(&mut self, file_id: NodeIndex) {
+        for scope in self.arena.iter_mut() {
+            for symbol in &mut scope.get_mut().symbols {
+                symbol.usages.retain(|usage| usage.file_id != Some(file_id));
+            }
+        }
+    }
 }
 
 impl SymbolTable {
-    pub fn new(ast: &mut Ast) -> SymbolTable {
+    pub fn new(ast: &mut Ast, uri: &Url) -> SymbolTable {
         let mut table = SymbolTable::default();
 
-        table.root_id = Some(table.parse_scope(ast.visit_root().get_id(), ast.get_arena()));
+        let root_id = table.parse_scope(ast.visit_root().get_id(), ast.get_arena(), uri);
+        // The root node may not cover leading or trailing whitespace, the root scope always does
+        let root_table = table.arena.get_mut(root_id).unwrap().get_mut();
+        root_table.range = document_range();
+        for symbol in &mut root_table.symbols {
+            symbol.visibility = Visibility::Exported;
+        }
+        table.root_id = Some(root_id);
         table.parse_usages(ast.get_arena());
         table.parse_types(ast.visit_root().get_id(), ast.get_arena());
         table.parse_member_usages(ast.visit_root().get_id(), ast.get_arena());
@@ -154,7 +208,7 @@ impl SymbolTable {
         for child_scope_id in current.children(&self.arena) {
             let child_scope = self.arena.get(child_scope_id).unwrap().get();
 
-            if position >= child_scope.range.start && position <= child_scope.range.end {
+            if child_scope.contains(position) {
                 if let Some(scope_id) = self._get_scope_id(position, child_scope_id) {
                     if scope_id.to_string() == self.root_id?.to_string() {
                         return Some(child_scope_id);
@@ -167,7 +221,7 @@ impl SymbolTable {
         self.root_id
     }
 
-    fn parse_scope(&mut self, node_id: NodeId, ast_arena: &mut Arena<Node>) -> ScopeId {
+    fn parse_scope(&mut self, node_id: NodeId, ast_arena: &mut Arena<Node>, uri: &Url) -> ScopeId {
         let table = ScopeSymbolTable::new(ast_arena.get(node_id).unwrap().get().range);
         let current_table_node_id = self.arena.new_node(table);
 
@@ -195,7 +249,20 @@ impl SymbolTable {
 
                 let name_node = ast_arena.get(name_node_id).unwrap().get();
 
-                let symbol = Symbol::new(name_node.content.clone(), kind.clone(), name_node.range);
+                let mut symbol = Symbol::new(
+                    name_node.content.clone(),
+                    kind.clone(),
+                    name_node.range,
+                    uri.clone(),
+                );
+                symbol.annotations = node_id
+                    .children(ast_arena)
+                    .map(|id| ast_arena.get(id).unwrap().get())
+                    .filter(|node| node.kind.is_annotation_node())
+                    .map(|node| node.content.trim().to_string())
+                    .collect();
+                symbol.readonly = is_readonly(kind, node_id, ast_arena);
+                symbol.expansion = get_expansion(kind, node_id, ast_arena);
 
                 let symbols = &mut self
                     .arena
@@ -218,7 +285,7 @@ impl SymbolTable {
             };
 
             if ast_arena.get(node_id).unwrap().get().kind.is_scope_node() {
-                let subtable = self.parse_scope(node_id, ast_arena);
+                let subtable = self.parse_scope(node_id, ast_arena, uri);
 
                 if let Some(i) = symbol_index {
                     self.arena
@@ -331,104 +398,60 @@ impl SymbolTable {
             .collect();
 
         for id in ids {
-            if let Some(previous_sibling_id) = arena.get(id).unwrap().previous_sibling() {
-                let previous_sibling = arena.get(previous_sibling_id).unwrap().get();
-                match previous_sibling.symbol {
-                    language_def::Symbol::Usage => {
-                        if let Some(symbol_id) = previous_sibling.linked_symbol.clone() {
-                            let parent_symbol = self.get_symbol(symbol_id).unwrap();
-
-                            if let Some(parent_type_symbol_id) = parent_symbol.type_symbol.clone() {
-                                let parent_type_symbol =
-                                    self.get_symbol(parent_type_symbol_id).unwrap();
-
-                                if let Some(field_scope_id) = parent_type_symbol.field_scope_id {
-                                    let scope_table =
-                                        self.arena.get_mut(field_scope_id).unwrap().get_mut();
-
-                                    if let Some(member_symbol_index) =
-                                        scope_table.symbols.iter().position(|s| {
-                                            s.name == arena.get(id).unwrap().get().content
-                                        })
-                                    {
-                                        arena
-                                            .get_mut(id)
-                                            .unwrap()
-                                            .get_mut()
-                                            .link(field_scope_id, member_symbol_index);
-                                        scope_table
-                                            .symbols
-                                            .get_mut(member_symbol_index)
-                                            .unwrap()
-                                            .usages
-                                            .push(Usage::new_local(
-                                                arena.get(id).unwrap().get().range,
-                                            ));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    language_def::Symbol::Expression => {
-                        if let Some(previous_symbol_id) =
-                            previous_sibling_id.children(arena).find(|id| {
-                                matches!(
-                                    arena.get(*id).unwrap().get().symbol,
-                                    language_def::Symbol::MemberUsage
-                                )
-                            })
-                        {
-                            if let Some(previous_sibling) = arena.get(previous_symbol_id) {
-                                if let Some(symbol_id) =
-                                    previous_sibling.get().linked_symbol.clone()
-                                {
-                                    let parent_symbol = self.get_symbol(symbol_id).unwrap();
-
-                                    if let Some(parent_type_symbol_id) =
-                                        parent_symbol.type_symbol.clone()
-                                    {
-                                        let parent_type_symbol =
-                                            self.get_symbol(parent_type_symbol_id).unwrap();
-
-                                        if let Some(field_scope_id) =
-                                            parent_type_symbol.field_scope_id
-                                        {
-                                            let scope_table = self
-                                                .arena
-                                                .get_mut(field_scope_id)
-                                                .unwrap()
-                                                .get_mut();
-
-                                            if let Some(member_symbol_index) =
-                                                scope_table.symbols.iter().position(|s| {
-                                                    s.name == arena.get(id).unwrap().get().content
-                                                })
-                                            {
-                                                arena
-                                                    .get_mut(id)
-                                                    .unwrap()
-                                                    .get_mut()
-                                                    .link(field_scope_id, member_symbol_index);
-                                                scope_table
-                                                    .symbols
-                                                    .get_mut(member_symbol_index)
-                                                    .unwrap()
-                                                    .usages
-                                                    .push(Usage::new_local(
-                                                        arena.get(id).unwrap().get().range,
-                                                    ));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+            let Some(previous_sibling_id) = arena.get(id).unwrap().previous_sibling() else {
+                continue;
+            };
+            let previous_sibling = arena.get(previous_sibling_id).unwrap().get();
+            let base_id = match previous_sibling.symbol {
+                language_def::Symbol::Usage => previous_sibling.linked_symbol.clone(),
+                language_def::Symbol::Expression => previous_sibling_id
+                    .children(arena)
+                    .find(|id| {
+                        matches!(
+                            arena.get(*id).unwrap().get().symbol,
+                            language_def::Symbol::MemberUsage
+                        )
+                    })
+                    .and_then(|id| arena.get(id).unwrap().get().linked_symbol.clone()),
+                _ => None,
+            };
+            let Some(field_scope_id) = base_id.and_then(|base_id| self.get_member_scope(base_id))
+            else {
+                continue;
+            };
+
+            let member = arena.get(id).unwrap().get();
+            let scope_table = self.arena.get_mut(field_scope_id).unwrap().get_mut();
+            if let Some(member_symbol_index) = scope_table
+                .symbols
+                .iter()
+                .position(|s| s.name == member.content)
+            {
+                scope_table.symbols[member_symbol_index]
+                    .usages
+                    .push(Usage::new_local(member.range));
+                arena
+                    .get_mut(id)
+                    .unwrap()
+                    .get_mut()
+                    .link(field_scope_id, member_symbol_index);
+            } else {
+                self.undefined_members
+                    .push((member.content.clone(), member.range));
             }
         }
     }
+
+    // The members of an instance are the fields of its type, those of a type like an enum or
+    // `error` are its own fields
+    fn get_member_scope(&self, base_id: SymbolId) -> Option<ScopeId> {
+        let base = self.get_symbol(base_id)?;
+
+        match base.type_symbol.clone() {
+            Some(type_id) => self.get_symbol(type_id)?.field_scope_id,
+            None => base.field_scope_id,
+        }
+    }
 }
 
 impl fmt::Display for SymbolTable {
@@ -436,7 +459,7 @@ impl fmt::Display for SymbolTable {
         let mut output = String::new();
 
         let mut sorted = self.arena.iter().collect::<Vec<_>>();
-        sorted.sort_by(|a, b| a.get().range.start.cmp(&b.get().range.start));
+        sorted.sort_by_key(|a| a.get().range.start);
 
         for node in sorted {
             output.push_str(format!("{}\n", node.get()).as_str());
@@ -459,11 +482,61 @@ impl ScopeSymbolTable {
             ..Default::default()
         }
     }
+
+    fn contains(&self, position: Position) -> bool {
+        position >= self.range.start && position <= self.range.end
+    }
+}
+
+fn is_readonly(kind: &str, declaration_id: NodeId, ast_arena: &Arena<Node>) -> bool {
+    language_def::LanguageDefinition::get()
+        .readonly_symbols
+        .iter()
+        .filter(|readonly| readonly.symbol_kind == kind)
+        .any(|readonly| {
+            let Some(marker_node) = &readonly.marker_node else {
+                return true;
+            };
+            declaration_id.children(ast_arena).any(|child_id| {
+                let child = ast_arena.get(child_id).unwrap().get();
+                child.kind == NodeKind::Node(marker_node.clone())
+                    && readonly
+                        .marker_content
+                        .as_ref()
+                        .is_none_or(|content| child.content.trim() == content)
+            })
+        })
+}
+
+// Range of the root scope, containing every position of the document
+fn document_range() -> Range {
+    Range::new(Position::new(0, 0), Position::new(u32::MAX, u32::MAX))
+}
+
+// Text a declaration of a macro kind expands to, from its value node
+fn get_expansion(kind: &str, declaration_id: NodeId, ast_arena: &Arena<Node>) -> Option<String> {
+    let value_node = &language_def::LanguageDefinition::get()
+        .macros
+        .iter()
+        .find(|macro_rule| macro_rule.symbol_kind == kind)?
+        .value_node;
+
+    declaration_id
+        .children(ast_arena)
+        .map(|id| ast_arena.get(id).unwrap().get())
+        .find(|node| node.kind == NodeKind::Node(value_node.clone()))
+        .map(|node| node.content.trim().to_string())
 }
 
 impl fmt::Display for ScopeSymbolTable {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let mut output = String::from("\n");
+        let mut output = format!(
+            "\nscope l:{} c:{} - l:{} c:{}\n",
+            self.range.start.line,
+            self.range.start.character,
+            self.range.end.line,
+            self.range.end.character
+        );
 
         output.push_str(
             format!(
@@ -483,3 +556,661 @@ impl fmt::Display for ScopeSymbolTable {
         fmt.write_str(&output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indextree::Arena;
+    use tower_lsp::lsp_types::{Location, Position, Range};
+
+    use super::{document_range, Actions, ScopeSymbolTable, SymbolTable};
+    use crate::language_def::{self, Symbol as SymbolRule};
+    use crate::project::metadata::test_utils::{init, node, range, symbol, test_url};
+    use crate::project::metadata::{Ast, Node, Visibility};
+
+    #[test]
+    fn test_type_parameter_in_parameter_list() {
+        language_def::load_test_definition();
+
+        // control C<H>(inout H hdr) {}
+        // H x;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let control = arena.new_node(node(
+            "Control",
+            "",
+            init("Control", None),
+            Range::new(Position::new(0, 0), Position::new(0, 29)),
+        ));
+        root.append(control, &mut arena);
+        control.append(
+            arena.new_node(node("Name", "C", SymbolRule::None, range(0, 8, 9))),
+            &mut arena,
+        );
+        let type_parameter = arena.new_node(node(
+            "TypeParameter",
+            "H",
+            init("Type", None),
+            range(0, 10, 11),
+        ));
+        type_parameter.append(
+            arena.new_node(node("Name", "H", SymbolRule::None, range(0, 10, 11))),
+            &mut arena,
+        );
+        control.append(type_parameter, &mut arena);
+        let parameter = arena.new_node(node(
+            "Parameter",
+            "",
+            init("Parameter", Some("Type")),
+            range(0, 13, 26),
+        ));
+        parameter.append(
+            arena.new_node(node("Type", "H", SymbolRule::Usage, range(0, 19, 20))),
+            &mut arena,
+        );
+        parameter.append(
+            arena.new_node(node("Name", "hdr", SymbolRule::None, range(0, 21, 24))),
+            &mut arena,
+        );
+        control.append(parameter, &mut arena);
+        root.append(
+            arena.new_node(node("Type", "H", SymbolRule::Usage, range(1, 0, 1))),
+            &mut arena,
+        );
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let hdr = table
+            .get_all_symbols()
+            .into_iter()
+            .find(|symbol| symbol.name == "hdr")
+            .unwrap();
+        let type_symbol = table.get_symbol(hdr.type_symbol.unwrap()).unwrap();
+        assert_eq!(type_symbol.name, "H");
+        assert_eq!(type_symbol.kind, "Type");
+
+        // Outside of the control, H isn't declared
+        assert_eq!(
+            table.get_unlinked_symbols(),
+            vec![(String::from("H"), range(1, 0, 1))]
+        );
+        assert!(table
+            .get_symbols_at_root()
+            .iter()
+            .all(|symbol| symbol.name != "H"));
+    }
+
+    #[test]
+    fn test_local_action_is_not_exported() {
+        language_def::load_test_definition();
+
+        // control C() { action drop() {} }
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let control = arena.new_node(node("Control", "", init("Control", None), range(0, 0, 32)));
+        root.append(control, &mut arena);
+        control.append(
+            arena.new_node(node("Name", "C", SymbolRule::None, range(0, 8, 9))),
+            &mut arena,
+        );
+        let action = arena.new_node(node("Action", "", init("Action", None), range(0, 14, 30)));
+        action.append(
+            arena.new_node(node("Name", "drop", SymbolRule::None, range(0, 21, 25))),
+            &mut arena,
+        );
+        control.append(action, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let visibility = |name: &str| {
+            table
+                .get_all_symbols()
+                .into_iter()
+                .find(|symbol| symbol.name == name)
+                .unwrap()
+                .visibility
+        };
+        assert_eq!(visibility("C"), Visibility::Exported);
+        assert_eq!(visibility("drop"), Visibility::Local);
+        // Including files only look among the root symbols
+        assert!(table
+            .get_symbols_at_root()
+            .iter()
+            .all(|symbol| symbol.name != "drop"));
+    }
+
+    #[test]
+    fn test_instantiation_resolves_later_use() {
+        language_def::load_test_definition();
+
+        // control Checksum16() {}
+        // Checksum16() ck;
+        // ck
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let control = arena.new_node(node("Control", "", init("Control", None), range(0, 0, 23)));
+        control.append(
+            arena.new_node(node(
+                "Name",
+                "Checksum16",
+                SymbolRule::None,
+                range(0, 8, 18),
+            )),
+            &mut arena,
+        );
+        root.append(control, &mut arena);
+        let instantiation = arena.new_node(node(
+            "Instantiation",
+            "",
+            init("Instance", Some("Type")),
+            range(1, 0, 16),
+        ));
+        instantiation.append(
+            arena.new_node(node(
+                "Type",
+                "Checksum16",
+                SymbolRule::Usage,
+                range(1, 0, 10),
+            )),
+            &mut arena,
+        );
+        instantiation.append(
+            arena.new_node(node("Name", "ck", SymbolRule::None, range(1, 13, 15))),
+            &mut arena,
+        );
+        root.append(instantiation, &mut arena);
+        let usage = arena.new_node(node("Usage", "ck", SymbolRule::Usage, range(2, 0, 2)));
+        root.append(usage, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let linked = ast.get_arena_ref()[usage]
+            .get()
+            .linked_symbol
+            .clone()
+            .unwrap();
+        let instance = table.get_symbol(linked).unwrap();
+        assert_eq!(instance.name, "ck");
+        assert_eq!(instance.def_range, range(1, 13, 15));
+
+        let type_symbol = table
+            .get_symbol(instance.type_symbol.clone().unwrap())
+            .unwrap();
+        assert_eq!(type_symbol.name, "Checksum16");
+        assert_eq!(type_symbol.def_range, range(0, 8, 18));
+        assert!(table.get_unlinked_symbols().is_empty());
+    }
+
+    #[test]
+    fn test_error_member_access() {
+        language_def::load_test_definition();
+
+        // error { PacketTooShort }
+        // error.PacketTooShort
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let declaration = arena.new_node(node("Error", "", init("Type", None), range(0, 0, 24)));
+        declaration.append(
+            arena.new_node(node("Name", "error", SymbolRule::None, range(0, 0, 5))),
+            &mut arena,
+        );
+        let member = arena.new_node(node("Member", "", init("Instance", None), range(0, 8, 22)));
+        member.append(
+            arena.new_node(node(
+                "Name",
+                "PacketTooShort",
+                SymbolRule::None,
+                range(0, 8, 22),
+            )),
+            &mut arena,
+        );
+        declaration.append(member, &mut arena);
+        root.append(declaration, &mut arena);
+        root.append(
+            arena.new_node(node("Usage", "error", SymbolRule::Usage, range(1, 0, 5))),
+            &mut arena,
+        );
+        let member_usage = arena.new_node(node(
+            "Member",
+            "PacketTooShort",
+            SymbolRule::MemberUsage,
+            range(1, 6, 20),
+        ));
+        root.append(member_usage, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let linked = ast.get_arena_ref()[member_usage]
+            .get()
+            .linked_symbol
+            .clone()
+            .unwrap();
+        let member = table.get_symbol(linked).unwrap();
+        assert_eq!(member.name, "PacketTooShort");
+        assert_eq!(member.usages.len(), 1);
+    }
+
+    #[test]
+    fn test_extern_method_call_declaration() {
+        language_def::load_test_definition();
+
+        // extern Counter { void count(); }
+        // Counter() c;
+        // c.count();
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let declaration = arena.new_node(node("Extern", "", init("Type", None), range(0, 0, 32)));
+        declaration.append(
+            arena.new_node(node("Name", "Counter", SymbolRule::None, range(0, 7, 14))),
+            &mut arena,
+        );
+        let method = arena.new_node(node("Method", "", init("Action", None), range(0, 17, 30)));
+        method.append(
+            arena.new_node(node("Name", "count", SymbolRule::None, range(0, 22, 27))),
+            &mut arena,
+        );
+        declaration.append(method, &mut arena);
+        root.append(declaration, &mut arena);
+        let instance = arena.new_node(node(
+            "Instance",
+            "",
+            init("Instance", Some("Type")),
+            range(1, 0, 12),
+        ));
+        instance.append(
+            arena.new_node(node("Type", "Counter", SymbolRule::Usage, range(1, 0, 7))),
+            &mut arena,
+        );
+        instance.append(
+            arena.new_node(node("Name", "c", SymbolRule::None, range(1, 10, 11))),
+            &mut arena,
+        );
+        root.append(instance, &mut arena);
+        root.append(
+            arena.new_node(node("Usage", "c", SymbolRule::Usage, range(2, 0, 1))),
+            &mut arena,
+        );
+        let method_call = arena.new_node(node(
+            "Member",
+            "count",
+            SymbolRule::MemberUsage,
+            range(2, 2, 7),
+        ));
+        root.append(method_call, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        // Going to the declaration of the call lands on the method's name in the extern
+        let linked = ast.get_arena_ref()[method_call]
+            .get()
+            .linked_symbol
+            .clone()
+            .unwrap();
+        let count = table.get_symbol(linked).unwrap();
+        assert_eq!(
+            count.get_location(),
+            Location::new(test_url(), range(0, 22, 27))
+        );
+    }
+
+    #[test]
+    fn test_package_and_main_instantiation() {
+        language_def::load_test_definition();
+
+        // control P() {}
+        // package Switch<H>(H p);
+        // Switch(P()) main;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let control = arena.new_node(node("Control", "", init("Control", None), range(0, 0, 14)));
+        control.append(
+            arena.new_node(node("Name", "P", SymbolRule::None, range(0, 8, 9))),
+            &mut arena,
+        );
+        root.append(control, &mut arena);
+        let package = arena.new_node(node("Package", "", init("Type", None), range(1, 0, 23)));
+        package.append(
+            arena.new_node(node("Name", "Switch", SymbolRule::None, range(1, 8, 14))),
+            &mut arena,
+        );
+        let type_parameter = arena.new_node(node(
+            "TypeParameter",
+            "H",
+            init("Type", None),
+            range(1, 15, 16),
+        ));
+        type_parameter.append(
+            arena.new_node(node("Name", "H", SymbolRule::None, range(1, 15, 16))),
+            &mut arena,
+        );
+        package.append(type_parameter, &mut arena);
+        let parameter = arena.new_node(node(
+            "Parameter",
+            "",
+            init("Parameter", Some("Type")),
+            range(1, 18, 21),
+        ));
+        let parameter_type = arena.new_node(node("Type", "H", SymbolRule::Usage, range(1, 18, 19)));
+        parameter.append(parameter_type, &mut arena);
+        parameter.append(
+            arena.new_node(node("Name", "p", SymbolRule::None, range(1, 20, 21))),
+            &mut arena,
+        );
+        package.append(parameter, &mut arena);
+        root.append(package, &mut arena);
+        let instantiation = arena.new_node(node(
+            "Instantiation",
+            "",
+            init("Instance", Some("Type")),
+            range(2, 0, 17),
+        ));
+        instantiation.append(
+            arena.new_node(node("Type", "Switch", SymbolRule::Usage, range(2, 0, 6))),
+            &mut arena,
+        );
+        let argument = arena.new_node(node("Usage", "P", SymbolRule::Usage, range(2, 7, 8)));
+        instantiation.append(argument, &mut arena);
+        instantiation.append(
+            arena.new_node(node("Name", "main", SymbolRule::None, range(2, 12, 16))),
+            &mut arena,
+        );
+        root.append(instantiation, &mut arena);
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let linked_name = |id| {
+            let symbol_id = ast.get_arena_ref()[id].get().linked_symbol.clone().unwrap();
+            let symbol = table.get_symbol(symbol_id).unwrap();
+            (symbol.name.clone(), symbol.def_range)
+        };
+        assert_eq!(
+            linked_name(parameter_type),
+            (String::from("H"), range(1, 15, 16))
+        );
+        assert_eq!(linked_name(argument), (String::from("P"), range(0, 8, 9)));
+
+        let main = table
+            .get_all_symbols()
+            .into_iter()
+            .find(|symbol| symbol.name == "main")
+            .unwrap();
+        let package_type = table.get_symbol(main.type_symbol.unwrap()).unwrap();
+        assert_eq!(package_type.name, "Switch");
+        assert!(table.get_unlinked_symbols().is_empty());
+    }
+
+    #[test]
+    fn test_method_body_in_instantiation() {
+        language_def::load_test_definition();
+
+        // Checksum16() ck = {
+        //     void compute(in H x) { return x; }
+        // };
+        // x;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let instantiation = arena.new_node(node(
+            "Instantiation",
+            "",
+            init("Instance", None),
+            Range::new(Position::new(0, 0), Position::new(2, 2)),
+        ));
+        instantiation.append(
+            arena.new_node(node("Name", "ck", SymbolRule::None, range(0, 13, 15))),
+            &mut arena,
+        );
+        root.append(instantiation, &mut arena);
+        let method = arena.new_node(node("Method", "", init("Action", None), range(1, 4, 38)));
+        method.append(
+            arena.new_node(node("Name", "compute", SymbolRule::None, range(1, 9, 16))),
+            &mut arena,
+        );
+        let parameter = arena.new_node(node(
+            "Parameter",
+            "",
+            init("Parameter", None),
+            range(1, 17, 23),
+        ));
+        parameter.append(
+            arena.new_node(node("Name", "x", SymbolRule::None, range(1, 22, 23))),
+            &mut arena,
+        );
+        method.append(parameter, &mut arena);
+        let inner_usage = arena.new_node(node("Usage", "x", SymbolRule::Usage, range(1, 34, 35)));
+        method.append(inner_usage, &mut arena);
+        instantiation.append(method, &mut arena);
+        root.append(
+            arena.new_node(node("Usage", "x", SymbolRule::Usage, range(3, 0, 1))),
+            &mut arena,
+        );
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let symbol_id = ast.get_arena_ref()[inner_usage]
+            .get()
+            .linked_symbol
+            .clone()
+            .unwrap();
+        let x = table.get_symbol(symbol_id).unwrap();
+        assert_eq!(
+            (x.kind.as_str(), x.def_range),
+            ("Parameter", range(1, 22, 23))
+        );
+        assert_eq!(
+            table.get_unlinked_symbols(),
+            vec![(String::from("x"), range(3, 0, 1))]
+        );
+    }
+
+    #[test]
+    fn test_constant_usage_count() {
+        language_def::load_test_definition();
+
+        // const N = 1;
+        // control C() { const N = 2; N; }
+        // N; N;
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        let constant = |arena: &mut Arena<Node>, line, start| {
+            let constant = arena.new_node(node(
+                "Constant",
+                "",
+                init("Instance", None),
+                range(line, start, start + 12),
+            ));
+            constant.append(
+                arena.new_node(node(
+                    "Name",
+                    "N",
+                    SymbolRule::None,
+                    range(line, start + 6, start + 7),
+                )),
+                arena,
+            );
+            constant
+        };
+        let usage = |arena: &mut Arena<Node>, line, start| {
+            arena.new_node(node(
+                "Usage",
+                "N",
+                SymbolRule::Usage,
+                range(line, start, start + 1),
+            ))
+        };
+        let outer = constant(&mut arena, 0, 0);
+        root.append(outer, &mut arena);
+        let control = arena.new_node(node("Control", "", init("Control", None), range(1, 0, 32)));
+        control.append(
+            arena.new_node(node("Name", "C", SymbolRule::None, range(1, 8, 9))),
+            &mut arena,
+        );
+        let inner = constant(&mut arena, 1, 14);
+        control.append(inner, &mut arena);
+        let inner_usage = usage(&mut arena, 1, 27);
+        control.append(inner_usage, &mut arena);
+        root.append(control, &mut arena);
+        for start in [0, 3] {
+            let outer_usage = usage(&mut arena, 2, start);
+            root.append(outer_usage, &mut arena);
+        }
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let usage_ranges = |line| {
+            let constant = table
+                .get_all_symbols()
+                .into_iter()
+                .find(|symbol| symbol.name == "N" && symbol.def_range.start.line == line)
+                .unwrap();
+            constant
+                .usages
+                .iter()
+                .map(|usage| usage.range)
+                .collect::<Vec<Range>>()
+        };
+        assert_eq!(usage_ranges(0), vec![range(2, 0, 1), range(2, 3, 4)]);
+        assert_eq!(usage_ranges(1), vec![range(1, 27, 28)]);
+    }
+
+    #[test]
+    fn test_actions_with_parameters() {
+        language_def::load_test_definition();
+
+        // action forward(port) {}
+        // action drop() {}
+        let mut arena = Arena::new();
+        let root = arena.new_node(node("Root", "", SymbolRule::None, range(0, 0, 0)));
+        for (line, name, parameters) in [(0, "forward", vec!["port"]), (1, "drop", vec![])] {
+            let action =
+                arena.new_node(node("Method", "", init("Action", None), range(line, 0, 23)));
+            action.append(
+                arena.new_node(node("Name", name, SymbolRule::None, range(line, 7, 14))),
+                &mut arena,
+            );
+            for parameter_name in parameters {
+                let parameter = arena.new_node(node(
+                    "Parameter",
+                    "",
+                    init("Parameter", None),
+                    range(line, 15, 19),
+                ));
+                parameter.append(
+                    arena.new_node(node(
+                        "Name",
+                        parameter_name,
+                        SymbolRule::None,
+                        range(line, 15, 19),
+                    )),
+                    &mut arena,
+                );
+                action.append(parameter, &mut arena);
+            }
+            root.append(action, &mut arena);
+        }
+
+        let mut ast = Ast::initialize(arena, root);
+        let table = SymbolTable::new(&mut ast, &test_url());
+
+        let mut actions = table.get_top_level_symbols();
+        actions.sort_by_key(|action| action.def_range.start);
+        let parameters: Vec<(&str, &str, Vec<String>)> = actions
+            .iter()
+            .map(|action| {
+                let parameters = table
+                    .get_symbols_in_scope(action.field_scope_id.unwrap())
+                    .into_iter()
+                    .map(|parameter| parameter.name)
+                    .collect();
+                (action.name.as_str(), action.kind.as_str(), parameters)
+            })
+            .collect();
+        assert_eq!(
+            parameters,
+            vec![
+                ("forward", "Action", vec![String::from("port")]),
+                ("drop", "Action", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scope_lookup_near_end_of_file() {
+        let mut arena = Arena::new();
+        let root_id = arena.new_node(ScopeSymbolTable::new(document_range()));
+
+        // `control ingress() {` on line 1 to `}` on the last line of the file
+        let mut control =
+            ScopeSymbolTable::new(Range::new(Position::new(1, 0), Position::new(5, 1)));
+        control
+            .symbols
+            .push(symbol("meta", "Variable", range(2, 4, 8)));
+        let control_id = arena.new_node(control);
+        root_id.append(control_id, &mut arena);
+
+        let table = SymbolTable {
+            arena,
+            root_id: Some(root_id),
+            undefined_list: vec![],
+            undefined_members: vec![],
+        };
+
+        let names: Vec<String> = table
+            .get_symbols_in_scope_at_pos(Position::new(5, 0))
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        assert_eq!(names, vec![String::from("meta")]);
+        assert_eq!(table.get_scope_id(Position::new(5, 0)), Some(control_id));
+        // Past the last node, positions are still in the root scope
+        assert_eq!(table.get_scope_id(Position::new(6, 3)), Some(root_id));
+    }
+
+    #[test]
+    fn test_shadowed_symbol_at_position() {
+        let variable = |line| symbol("x", "Variable", range(line, 4, 5));
+        let mut arena = Arena::new();
+        let mut root = ScopeSymbolTable::new(document_range());
+        root.symbols.push(variable(0));
+        let root_id = arena.new_node(root);
+
+        let mut control =
+            ScopeSymbolTable::new(Range::new(Position::new(1, 0), Position::new(7, 1)));
+        control.symbols.push(variable(2));
+        let control_id = arena.new_node(control);
+        root_id.append(control_id, &mut arena);
+
+        let mut action =
+            ScopeSymbolTable::new(Range::new(Position::new(3, 4), Position::new(6, 5)));
+        action.symbols.push(variable(4));
+        let action_id = arena.new_node(action);
+        control_id.append(action_id, &mut arena);
+
+        let table = SymbolTable {
+            arena,
+            root_id: Some(root_id),
+            undefined_list: vec![],
+            undefined_members: vec![],
+        };
+        let declaration_line = |line, character| {
+            table
+                .get_symbol_at_pos(String::from("x"), Position::new(line, character))
+                .map(|symbol| symbol.def_range.start.line)
+        };
+
+        assert_eq!(declaration_line(5, 8), Some(4));
+        // In the action, before its own declaration
+        assert_eq!(declaration_line(3, 8), Some(2));
+        assert_eq!(declaration_line(1, 8), Some(0));
+        assert_eq!(declaration_line(0, 0), None);
+        assert!(table
+            .get_symbol_at_pos(String::from("y"), Position::new(5, 8))
+            .is_none());
+    }
+}
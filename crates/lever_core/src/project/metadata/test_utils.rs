@@ -0,0 +1,52 @@
+//! Builders of the nodes and symbols the tests put together by hand.
+
+use tower_lsp::lsp_types::{Position, Range, Url};
+
+use super::{Node, NodeKind, Symbol};
+use crate::language_def::{CompletionRule, Import, Symbol as SymbolRule};
+use crate::project::features::completion::RuleContext;
+
+/// Url of the file the test nodes and symbols belong to.
+pub fn test_url() -> Url {
+    Url::parse("file:///test.p4").unwrap()
+}
+
+pub fn range(line: u32, start: u32, end: u32) -> Range {
+    Range::new(Position::new(line, start), Position::new(line, end))
+}
+
+pub fn node(kind: &str, content: &str, symbol: SymbolRule, range: Range) -> Node {
+    Node {
+        kind: NodeKind::Node(kind.to_string()),
+        range,
+        content: content.to_string(),
+        symbol,
+        import: Import::None,
+        semantic_token_type: None,
+        linked_symbol: None,
+    }
+}
+
+/// Rule declaring a symbol named by the `Name` child, typed by the `type_node` child if any.
+pub fn init(kind: &str, type_node: Option<&str>) -> SymbolRule {
+    SymbolRule::Init {
+        kind: kind.to_string(),
+        name_node: String::from("Name"),
+        type_node: type_node.map(String::from),
+    }
+}
+
+/// Context of a completion rule, whose node already uses `used_names`. Contexts borrow their rule
+/// from the language definition, so the rule is leaked.
+pub fn rule_context(rule: CompletionRule, used_names: &[&str]) -> RuleContext {
+    RuleContext {
+        rule: Box::leak(Box::new(rule)),
+        used_names: used_names.iter().map(|name| name.to_string()).collect(),
+        target_symbol: None,
+    }
+}
+
+/// Symbol declared in the test file.
+pub fn symbol(name: &str, kind: &str, range: Range) -> Symbol {
+    Symbol::new(name.to_string(), kind.to_string(), range, test_url())
+}
@@ -3,7 +3,11 @@ pub mod workspace;
 mod features;
 mod file;
 mod metadata;
+mod parser_pool;
+#[cfg(test)]
+mod test_language;
 
+pub use features::semantic_tokens::ColorData;
 pub use metadata::{
     Ast, AstQuery, Node, NodeKind, SymbolTableQuery, Translator, VisitNode, Visitable,
 };
@@ -0,0 +1,44 @@
+use std::sync::Mutex;
+
+use tree_sitter::{Language, Parser, Tree};
+
+/// Parsers configured for the workspace's language. Files borrow one to parse and give it back
+/// afterwards, so parsers are only created when several files are parsed at the same time.
+pub struct ParserPool {
+    language: Language,
+    parsers: Mutex<Vec<Parser>>,
+}
+
+impl ParserPool {
+    pub fn new(language: Language) -> ParserPool {
+        ParserPool {
+            language,
+            parsers: Mutex::new(vec![]),
+        }
+    }
+
+    /// Parses the text, returning None if tree-sitter gave up on it.
+    pub fn parse(&self, text: &str, old_tree: Option<&Tree>) -> Option<Tree> {
+        let mut parser = self.parsers.lock().unwrap().pop().unwrap_or_else(|| {
+            let mut parser = Parser::new();
+            parser.set_language(self.language).unwrap();
+            parser
+        });
+
+        let tree = parser.parse(text, old_tree);
+        if tree.is_none() {
+            // A failed parse leaves the parser in the middle of it
+            parser.reset();
+        }
+        self.parsers.lock().unwrap().push(parser);
+
+        tree
+    }
+
+    /// Tree of an empty text, for files that aren't indexed.
+    pub fn parse_empty(&self) -> Tree {
+        // Parsing only fails on timeouts and cancellations, which aren't used
+        self.parse("", None)
+            .expect("Parsing an empty text shouldn't fail.")
+    }
+}
@@ -0,0 +1,274 @@
+//! Tree-sitter language for the tests, since the crate doesn't ship a grammar. It parses
+//! `source_file: repeat(word)`, a word being anything other than whitespace, from parse tables
+//! written by hand like `tree-sitter generate` would.
+
+use std::ffi::c_char;
+use std::ptr;
+
+const SYMBOL_COUNT: usize = 4;
+const STATE_COUNT: usize = 6;
+
+const SYM_END: u16 = 0;
+const SYM_WORD: u16 = 1;
+const SYM_SOURCE_FILE: u16 = 2;
+const AUX_SYM_WORDS: u16 = 3;
+
+#[repr(C)]
+struct TSLexer {
+    lookahead: i32,
+    result_symbol: u16,
+    advance: unsafe extern "C" fn(*mut TSLexer, bool),
+    mark_end: unsafe extern "C" fn(*mut TSLexer),
+    get_column: unsafe extern "C" fn(*mut TSLexer) -> u32,
+    is_at_included_range_start: unsafe extern "C" fn(*const TSLexer) -> bool,
+    eof: unsafe extern "C" fn(*const TSLexer) -> bool,
+}
+
+// A TSParseActionEntry, either the header of a group of actions or one of them
+#[repr(C, align(2))]
+struct ActionEntry([u8; 8]);
+
+const fn header(count: u8) -> ActionEntry {
+    ActionEntry([count, true as u8, 0, 0, 0, 0, 0, 0])
+}
+
+const fn shift(state: u16) -> ActionEntry {
+    let [low, high] = state.to_ne_bytes();
+    ActionEntry([0, 0, low, high, 0, 0, 0, 0])
+}
+
+const fn reduce(symbol: u16, child_count: u8) -> ActionEntry {
+    let [low, high] = symbol.to_ne_bytes();
+    ActionEntry([1, child_count, low, high, 0, 0, 0, 0])
+}
+
+const fn accept() -> ActionEntry {
+    ActionEntry([2, 0, 0, 0, 0, 0, 0, 0])
+}
+
+const fn recover() -> ActionEntry {
+    ActionEntry([3, 0, 0, 0, 0, 0, 0, 0])
+}
+
+static PARSE_ACTIONS: [ActionEntry; 17] = [
+    ActionEntry([0; 8]),
+    // 1: error recovery
+    ActionEntry([1, 0, 0, 0, 0, 0, 0, 0]),
+    recover(),
+    // 3: empty file
+    header(1),
+    reduce(SYM_SOURCE_FILE, 0),
+    // 5: first word
+    header(1),
+    shift(2),
+    // 7: words -> word
+    header(1),
+    reduce(AUX_SYM_WORDS, 1),
+    // 9: source_file -> words
+    header(1),
+    reduce(SYM_SOURCE_FILE, 1),
+    // 11: next word
+    header(1),
+    shift(5),
+    // 13
+    header(1),
+    accept(),
+    // 15: words -> words word
+    header(1),
+    reduce(AUX_SYM_WORDS, 2),
+];
+
+// Action group of each token and next state after each rule, by state then symbol
+static PARSE_TABLE: [[u16; SYMBOL_COUNT]; STATE_COUNT] = [
+    [1, 1, 0, 0],
+    [3, 5, 4, 3],
+    [7, 7, 0, 0],
+    [9, 11, 0, 0],
+    [13, 0, 0, 0],
+    [15, 15, 0, 0],
+];
+
+#[repr(C)]
+struct SymbolMetadata {
+    visible: bool,
+    named: bool,
+    supertype: bool,
+}
+
+static SYMBOL_METADATA: [SymbolMetadata; SYMBOL_COUNT] = [
+    SymbolMetadata {
+        visible: false,
+        named: true,
+        supertype: false,
+    },
+    SymbolMetadata {
+        visible: true,
+        named: true,
+        supertype: false,
+    },
+    SymbolMetadata {
+        visible: true,
+        named: true,
+        supertype: false,
+    },
+    SymbolMetadata {
+        visible: false,
+        named: false,
+        supertype: false,
+    },
+];
+
+struct Names([*const c_char; SYMBOL_COUNT]);
+
+// The names are static strings
+unsafe impl Sync for Names {}
+
+static SYMBOL_NAMES: Names = Names([
+    c"end".as_ptr(),
+    c"word".as_ptr(),
+    c"source_file".as_ptr(),
+    c"source_file_repeat1".as_ptr(),
+]);
+
+static PUBLIC_SYMBOL_MAP: [u16; SYMBOL_COUNT] = [0, 1, 2, 3];
+static PRIMARY_STATE_IDS: [u16; STATE_COUNT] = [0, 1, 2, 3, 4, 5];
+static LEX_MODES: [[u16; 2]; STATE_COUNT] = [[0, 0]; STATE_COUNT];
+// Neither fields nor aliases, the tables only hold their terminator
+static NONE: [u16; 2] = [0, 0];
+
+#[repr(C)]
+struct ExternalScanner {
+    states: *const bool,
+    symbol_map: *const u16,
+    create: Option<unsafe extern "C" fn() -> *mut ()>,
+    destroy: Option<unsafe extern "C" fn(*mut ())>,
+    scan: Option<unsafe extern "C" fn(*mut (), *mut TSLexer, *const bool) -> bool>,
+    serialize: Option<unsafe extern "C" fn(*mut (), *mut c_char) -> u32>,
+    deserialize: Option<unsafe extern "C" fn(*mut (), *const c_char, u32)>,
+}
+
+// Layout of the TSLanguage of ABI 14
+#[repr(C)]
+struct TSLanguage {
+    version: u32,
+    symbol_count: u32,
+    alias_count: u32,
+    token_count: u32,
+    external_token_count: u32,
+    state_count: u32,
+    large_state_count: u32,
+    production_id_count: u32,
+    field_count: u32,
+    max_alias_sequence_length: u16,
+    parse_table: *const u16,
+    small_parse_table: *const u16,
+    small_parse_table_map: *const u32,
+    parse_actions: *const ActionEntry,
+    symbol_names: *const *const c_char,
+    field_names: *const *const c_char,
+    field_map_slices: *const u16,
+    field_map_entries: *const u16,
+    symbol_metadata: *const SymbolMetadata,
+    public_symbol_map: *const u16,
+    alias_map: *const u16,
+    alias_sequences: *const u16,
+    lex_modes: *const [u16; 2],
+    lex_fn: unsafe extern "C" fn(*mut TSLexer, u16) -> bool,
+    keyword_lex_fn: Option<unsafe extern "C" fn(*mut TSLexer, u16) -> bool>,
+    keyword_capture_token: u16,
+    external_scanner: ExternalScanner,
+    primary_state_ids: *const u16,
+}
+
+// All the pointers are to static tables
+unsafe impl Sync for TSLanguage {}
+
+static LANGUAGE: TSLanguage = TSLanguage {
+    version: 14,
+    symbol_count: SYMBOL_COUNT as u32,
+    alias_count: 0,
+    token_count: 2,
+    external_token_count: 0,
+    state_count: STATE_COUNT as u32,
+    // Every state is large, so the small tables are never read
+    large_state_count: STATE_COUNT as u32,
+    production_id_count: 1,
+    field_count: 0,
+    max_alias_sequence_length: 0,
+    parse_table: PARSE_TABLE.as_ptr().cast(),
+    small_parse_table: NONE.as_ptr(),
+    small_parse_table_map: ptr::null(),
+    parse_actions: PARSE_ACTIONS.as_ptr(),
+    symbol_names: SYMBOL_NAMES.0.as_ptr(),
+    field_names: ptr::null(),
+    field_map_slices: NONE.as_ptr(),
+    field_map_entries: NONE.as_ptr(),
+    symbol_metadata: SYMBOL_METADATA.as_ptr(),
+    public_symbol_map: PUBLIC_SYMBOL_MAP.as_ptr(),
+    alias_map: NONE.as_ptr(),
+    alias_sequences: NONE.as_ptr(),
+    lex_modes: LEX_MODES.as_ptr(),
+    lex_fn: lex,
+    keyword_lex_fn: None,
+    keyword_capture_token: 0,
+    external_scanner: ExternalScanner {
+        states: ptr::null(),
+        symbol_map: ptr::null(),
+        create: None,
+        destroy: None,
+        scan: None,
+        serialize: None,
+        deserialize: None,
+    },
+    primary_state_ids: PRIMARY_STATE_IDS.as_ptr(),
+};
+
+fn is_whitespace(lookahead: i32) -> bool {
+    char::from_u32(lookahead as u32).is_some_and(char::is_whitespace)
+}
+
+unsafe extern "C" fn lex(lexer: *mut TSLexer, _state: u16) -> bool {
+    while is_whitespace((*lexer).lookahead) {
+        ((*lexer).advance)(lexer, true);
+    }
+
+    if ((*lexer).eof)(lexer) {
+        (*lexer).result_symbol = SYM_END;
+    } else {
+        while !((*lexer).eof)(lexer) && !is_whitespace((*lexer).lookahead) {
+            ((*lexer).advance)(lexer, false);
+        }
+        (*lexer).result_symbol = SYM_WORD;
+    }
+    ((*lexer).mark_end)(lexer);
+
+    true
+}
+
+pub fn language() -> tree_sitter::Language {
+    // tree_sitter::Language only wraps the pointer to the C struct
+    unsafe { std::mem::transmute::<*const TSLanguage, tree_sitter::Language>(&LANGUAGE) }
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_sitter::Parser;
+
+    use super::language;
+
+    #[test]
+    fn test_parse_words() {
+        let mut parser = Parser::new();
+        parser.set_language(language()).unwrap();
+
+        let tree = parser.parse("control ingress\n  apply", None).unwrap();
+        assert_eq!(
+            tree.root_node().to_sexp(),
+            "(source_file (word) (word) (word))"
+        );
+        assert_eq!(
+            parser.parse("", None).unwrap().root_node().to_sexp(),
+            "(source_file)"
+        );
+    }
+}
@@ -1,30 +1,63 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
 
-use petgraph::visit::EdgeRef;
+use indextree::{Arena, NodeId};
+use petgraph::visit::{Bfs, EdgeRef};
 use petgraph::EdgeDirection;
 use petgraph::{dot::Dot, prelude::NodeIndex, Graph};
-use serde_json::Value;
+use serde_json::{json, Value};
 use tower_lsp::lsp_types::{
-    CompletionContext, CompletionItem, CompletionTriggerKind, Diagnostic, HoverContents, Location,
-    MarkedString, Position, Range, SemanticTokensResult, TextDocumentContentChangeEvent, TextEdit,
-    Url, WorkspaceEdit,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionResponse, CompletionContext,
+    CompletionItem, CompletionList, Diagnostic, DiagnosticSeverity, DocumentHighlight,
+    DocumentSymbolResponse, FoldingRange, HoverContents, InlayHint, Location, NumberOrString,
+    Position, Range, SemanticTokensResult, SignatureHelp, SymbolInformation,
+    TextDocumentContentChangeEvent, TextEdit, Url, WorkspaceEdit,
 };
 
-use super::metadata::{AstEditor, AstQuery, SymbolId, SymbolTableQuery, Usage, Visitable};
+use super::metadata::{
+    AstEditor, AstQuery, Node, NodeKind, ScopeId, Symbol, SymbolId, SymbolTableEditor,
+    SymbolTableQuery, Usage, Visibility, VisitNode, Visitable,
+};
+use crate::language_def::{self, LanguageDefinition};
 use crate::settings::Settings;
+use crate::utils::{self, ResolvedInclude};
 
+use super::features::semantic_tokens::ColorData;
+use super::features::{
+    code_actions, completion, diagnostics, hover, inlay_hints, references, signature_help,
+    workspace_symbols,
+};
 use super::file::File;
+use super::parser_pool::ParserPool;
 
 pub trait FileManagement {
     fn get_file(&self, url: &Url) -> Option<&File>;
     fn get_file_mut(&mut self, url: &Url) -> Option<&mut File>;
     fn add_file(&mut self, url: Url, content: &str);
-    fn update_file(&mut self, url: &Url, changes: Vec<TextDocumentContentChangeEvent>);
+    fn update_file(
+        &mut self,
+        url: &Url,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Option<()>;
 }
 
 pub trait LanguageActions {
-    fn get_definition_location(&self, url: &Url, symbol_position: Position) -> Option<Location>;
-    fn get_semantic_tokens(&self, url: &Url) -> Option<SemanticTokensResult>;
+    fn get_declaration_location(&self, url: &Url, symbol_position: Position) -> Option<Location>;
+    fn get_definition_locations(&self, url: &Url, symbol_position: Position) -> Vec<Location>;
+    fn get_type_definition_location(
+        &self,
+        url: &Url,
+        symbol_position: Position,
+    ) -> Option<Location>;
+    fn get_semantic_tokens(
+        &self,
+        url: &Url,
+        extra_tokens: Vec<ColorData>,
+    ) -> Option<SemanticTokensResult>;
+    fn prepare_rename(&self, url: &Url, position: Position) -> Option<Range>;
     fn rename_symbol(
         &mut self,
         url: &Url,
@@ -36,8 +69,27 @@ pub trait LanguageActions {
         url: &Url,
         position: Position,
         context: Option<CompletionContext>,
-    ) -> Option<Vec<CompletionItem>>;
+        plugin_items: Vec<CompletionItem>,
+    ) -> Option<CompletionList>;
     fn get_hover_info(&self, url: &Url, position: Position) -> Option<HoverContents>;
+    fn get_references(
+        &self,
+        url: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>>;
+    fn get_document_highlights(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<DocumentHighlight>>;
+    fn get_inlay_hints(&self, url: &Url, range: Range) -> Option<Vec<InlayHint>>;
+    fn get_signature_help(&self, url: &Url, position: Position) -> Option<SignatureHelp>;
+    fn get_code_actions(&self, url: &Url, range: Range) -> Option<CodeActionResponse>;
+    fn get_workspace_symbols(&self, query: &str) -> Vec<SymbolInformation>;
+    fn get_document_symbols(&self, url: &Url, hierarchical: bool)
+        -> Option<DocumentSymbolResponse>;
+    fn get_folding_ranges(&self, url: &Url) -> Vec<FoldingRange>;
     fn get_quick_diagnostics(&self, url: &Url) -> Vec<Diagnostic>;
     fn get_full_diagnostics(&self, url: &Url) -> Vec<Diagnostic>;
 }
@@ -48,12 +100,16 @@ pub enum Import {
     Library,
 }
 
+// Borrowed from the handler holding the backend's lock. The managers of the files are locked for
+// one statement at a time, so that the helpers called meanwhile can lock them again.
 pub struct Workspace {
     root_path: Option<PathBuf>,
     settings: Settings,
+    naming_conventions: BTreeMap<String, diagnostics::NamingConvention>,
     url_node_map: HashMap<Url, NodeIndex>,
     file_graph: Graph<File, Import>,
-    tree_sitter_language: tree_sitter::Language,
+    parser_pool: ParserPool,
+    open_files: HashSet<Url>,
 }
 
 impl Workspace {
@@ -61,9 +117,11 @@ impl Workspace {
         Workspace {
             root_path: None,
             settings: Settings::default(),
+            naming_conventions: BTreeMap::new(),
             url_node_map: HashMap::new(),
             file_graph: Graph::new(),
-            tree_sitter_language,
+            parser_pool: ParserPool::new(tree_sitter_language),
+            open_files: HashSet::new(),
         }
     }
 
@@ -71,19 +129,620 @@ impl Workspace {
         self.root_path = path;
     }
 
+    pub fn get_root_path(&self) -> Option<&Path> {
+        self.root_path.as_deref()
+    }
+
+    pub const fn get_settings(&self) -> &Settings {
+        &self.settings
+    }
+
     pub fn update_settings(&mut self, settings: Value) {
         self.settings = Settings::parse(settings);
+        self.settings.include_paths = resolve_include_paths(
+            std::mem::take(&mut self.settings.include_paths),
+            self.root_path.as_deref(),
+        );
+        self.naming_conventions =
+            diagnostics::compile_naming_conventions(&self.settings.naming_conventions);
         info!("Settings: {:?}", self.settings);
     }
 
+    /// Files of the workspace that are indexed, to be diagnosed.
+    pub fn get_indexed_files(&self) -> Vec<Url> {
+        self.file_graph
+            .node_weights()
+            .filter(|file| file.is_indexed())
+            .map(|file| file.uri.clone())
+            .collect()
+    }
+
+    /// Computes the quick diagnostics of every indexed file that isn't open, when eager diagnostics
+    /// are enabled. Open files already get theirs as they are edited.
+    pub fn get_eager_diagnostics(&self) -> HashMap<Url, Vec<Diagnostic>> {
+        if !self.settings.eager_diagnostics {
+            return HashMap::new();
+        }
+
+        self.file_graph
+            .node_weights()
+            .filter(|file| file.is_indexed() && !self.open_files.contains(&file.uri))
+            .map(|file| (file.uri.clone(), file.get_quick_diagnostics()))
+            .collect()
+    }
+
+    /// Includes between the files of the workspace, to find out when they change.
+    pub fn get_include_edges(&self) -> HashSet<(Url, Url)> {
+        self.file_graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    self.file_graph[edge.source()].uri.clone(),
+                    self.file_graph[edge.target()].uri.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Symbols declared in a file, in every scope, without those it only sees through includes.
+    pub fn file_symbols(&self, url: &Url) -> Option<Vec<Symbol>> {
+        let file = self.get_file(url)?;
+        let symbols = file.symbol_table_manager.lock().unwrap().get_all_symbols();
+
+        Some(symbols)
+    }
+
+    fn get_symbol_hover_info(&self, url: &Url, position: Position) -> Option<HoverContents> {
+        let file_index = *self.url_node_map.get(url)?;
+        let file = self.get_file(url)?;
+        if !file.is_hoverable(position) {
+            return None;
+        }
+
+        let Some(symbol_id) = file.get_symbol_id_at_pos(position) else {
+            return file
+                .get_base_type_info(position)
+                .or_else(|| file.get_fallback_hover_info(position));
+        };
+        let (symbol_file_index, symbol) = self.get_symbol(file_index, &symbol_id)?;
+
+        // A type that can't be found or resolved only loses its part of the hover
+        let written_type = symbol
+            .type_symbol
+            .as_ref()
+            .and_then(|type_symbol_id| self.get_symbol(symbol_file_index, type_symbol_id));
+        let contents = match (&symbol.type_symbol, written_type) {
+            (Some(type_symbol_id), Some(written_type)) => {
+                let resolved_type = self.resolve_type(symbol_file_index, type_symbol_id).filter(
+                    |(index, resolved)| {
+                        *index != written_type.0 || resolved.def_range != written_type.1.def_range
+                    },
+                );
+
+                hover::get_symbol_info(
+                    &symbol,
+                    Some(&written_type.1),
+                    resolved_type.as_ref().map(|(_, resolved)| resolved),
+                )
+            }
+            _ => hover::get_symbol_info(&symbol, None, None),
+        };
+
+        let declaring_file = self.file_graph.node_weight(symbol_file_index)?;
+        Some(match declaring_file.get_line(symbol.def_range.start.line) {
+            Some(line) => hover::append_declaration(
+                contents,
+                &LanguageDefinition::get().language.name.to_lowercase(),
+                line,
+            ),
+            None => contents,
+        })
+    }
+
+    fn get_completion_items(
+        &self,
+        url: &Url,
+        position: Position,
+        context: Option<CompletionContext>,
+    ) -> Option<Vec<CompletionItem>> {
+        let file_index = *self.url_node_map.get(url)?;
+        let file = self.get_file(url)?;
+
+        if let Some(items) = file.get_directive_completion_list(position) {
+            return Some(items);
+        }
+
+        // Before the file could be parsed, or when nothing could be made of it, the language
+        // itself still has words to offer
+        let has_symbols = file.is_indexed()
+            && (!file
+                .symbol_table_manager
+                .lock()
+                .unwrap()
+                .get_all_symbols()
+                .is_empty()
+                || self
+                    .file_graph
+                    .edges_directed(file_index, EdgeDirection::Outgoing)
+                    .next()
+                    .is_some());
+        let trigger = completion::get_trigger(context.as_ref(), |character| {
+            LanguageDefinition::get().is_rule_trigger_character(character)
+        });
+        if !has_symbols && trigger == completion::Trigger::Names {
+            let language_def = LanguageDefinition::get();
+            return Some(completion::fallback_list(
+                &language_def.keywords,
+                &language_def.directives,
+            ));
+        }
+
+        // After a `.`, the fields of the name before it, whose type can be an alias or come from
+        // an included file. Each name of a chain like `hdr.ipv4.` is linked to its field.
+        if trigger == completion::Trigger::Member {
+            if let Some(fields) = position
+                .character
+                .checked_sub(1)
+                .and_then(|character| {
+                    file.get_symbol_id_at_pos(Position::new(position.line, character))
+                })
+                .and_then(|base_id| self.get_fields(file_index, &base_id))
+            {
+                return Some(completion::member_list(fields));
+            }
+        }
+
+        let rule_context = file.get_completion_context(position);
+        // Initializers offer the fields of their target, or the usual names if it isn't resolved
+        if let Some(rule_context) = &rule_context {
+            if let Some(fields) = rule_context
+                .target_symbol
+                .as_ref()
+                .and_then(|target_id| self.get_fields(file_index, target_id))
+            {
+                return Some(completion::field_list(fields, rule_context));
+            }
+        }
+
+        // A character triggering the rule of the context, like `<` for type arguments, completes
+        // names the same way as an explicit request
+        let completes_names = match &trigger {
+            completion::Trigger::Member => false,
+            completion::Trigger::RuleCharacter(character) => rule_context
+                .as_ref()
+                .is_some_and(|rule_context| rule_context.rule.is_triggered_by(character)),
+            completion::Trigger::Names => true,
+        };
+
+        if completes_names {
+            if let Some(mut items) = file.get_completion_list(position, context) {
+                for edge in self
+                    .file_graph
+                    .edges_directed(file_index, EdgeDirection::Outgoing)
+                    .filter(|edge| {
+                        self.settings.completion_include_builtins
+                            || !matches!(edge.weight(), Import::Library)
+                    })
+                {
+                    let imported_file = self.file_graph.node_weight(edge.target()).unwrap();
+                    items.append(
+                        &mut imported_file.get_import_completion_list(rule_context.as_ref()),
+                    );
+                }
+                Some(items)
+            } else {
+                None
+            }
+        } else {
+            file.get_completion_list(position, context)
+        }
+    }
+
+    /// Usages of the symbol at the position grouped by file, with the line of each usage.
+    pub fn get_grouped_references(&self, url: &Url, position: Position) -> Option<Value> {
+        let (_, usages) = self.find_symbol_occurrences(url, position)?;
+
+        let groups =
+            references::group_by_file(usages, |uri| Some(self.get_file(uri)?.source_code.as_str()));
+
+        serde_json::to_value(groups).ok()
+    }
+
+    /// Fields of the symbol if it is a type, or else of its type, with the names of their types.
+    fn get_fields(
+        &self,
+        file_index: NodeIndex,
+        symbol_id: &SymbolId,
+    ) -> Option<Vec<(Symbol, Option<String>)>> {
+        let (mut owner_index, mut symbol) = self.get_symbol(file_index, symbol_id)?;
+        if symbol.field_scope_id.is_none() {
+            (owner_index, symbol) = self.resolve_type(owner_index, symbol.type_symbol.as_ref()?)?;
+        }
+
+        let fields = self
+            .file_graph
+            .node_weight(owner_index)?
+            .symbol_table_manager
+            .lock()
+            .unwrap()
+            .get_symbols_in_scope(symbol.field_scope_id?);
+
+        Some(
+            fields
+                .into_iter()
+                .map(|field| {
+                    let type_name = field
+                        .type_symbol
+                        .as_ref()
+                        .and_then(|type_id| self.get_symbol(owner_index, type_id))
+                        .map(|(_, type_symbol)| type_symbol.name);
+                    (field, type_name)
+                })
+                .collect(),
+        )
+    }
+
+    /// Names of the parameters of a callee with the names of their types, in the order they are
+    /// declared.
+    fn get_parameters(
+        &self,
+        file_index: NodeIndex,
+        callee: &SymbolId,
+        parameter_kind: &str,
+    ) -> Option<Vec<(String, Option<String>)>> {
+        let (owner_index, callee) = self.get_symbol(file_index, callee)?;
+
+        let mut parameters: Vec<Symbol> = self
+            .file_graph
+            .node_weight(owner_index)?
+            .symbol_table_manager
+            .lock()
+            .unwrap()
+            .get_symbols_in_scope(callee.field_scope_id?)
+            .into_iter()
+            .filter(|symbol| symbol.kind == parameter_kind)
+            .collect();
+        parameters.sort_by_key(|symbol| symbol.def_range.start);
+
+        Some(
+            parameters
+                .into_iter()
+                .map(|symbol| {
+                    let type_name = symbol
+                        .type_symbol
+                        .as_ref()
+                        .and_then(|type_id| self.get_symbol(owner_index, type_id))
+                        .map(|(_, type_symbol)| type_symbol.name);
+                    (symbol.name, type_name)
+                })
+                .collect(),
+        )
+    }
+
+    /// Declaration and usages of the symbol at the position, which can be on the declaration as
+    /// well as on any usage.
+    fn find_symbol_occurrences(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<(Location, Vec<Location>)> {
+        let file_index = *self.url_node_map.get(url)?;
+        let symbol_id = self.get_file(url)?.get_symbol_id_at_pos(position)?;
+        let (owner_index, symbol) = self.get_symbol(file_index, &symbol_id)?;
+
+        Some((
+            symbol.get_location(),
+            self.get_usage_locations(owner_index, &symbol),
+        ))
+    }
+
+    /// Locations of the top-level declarations named `name` in the file at `file_index` or, if it
+    /// has none, in the nearest file it includes that exports some.
+    fn find_nearest_declaration(&self, file_index: NodeIndex, name: &str) -> Vec<Location> {
+        let get_includes = |index: NodeIndex| {
+            self.file_graph
+                .neighbors_directed(index, EdgeDirection::Outgoing)
+                .collect()
+        };
+        let find = |index: NodeIndex| {
+            let (symbols, _) = self
+                .file_graph
+                .node_weight(index)?
+                .symbol_table_manager
+                .lock()
+                .unwrap()
+                .get_symbols_at_root();
+            let locations: Vec<Location> = symbols
+                .iter()
+                .filter(|symbol| {
+                    symbol.name == name
+                        && (index == file_index || symbol.visibility == Visibility::Exported)
+                })
+                .map(Symbol::get_location)
+                .collect();
+            (!locations.is_empty()).then_some(locations)
+        };
+
+        find_nearest(file_index, get_includes, find).unwrap_or_default()
+    }
+
+    /// Locations of the usages of a symbol declared in the file at `owner_index`.
+    fn get_usage_locations(&self, owner_index: NodeIndex, symbol: &Symbol) -> Vec<Location> {
+        symbol
+            .usages
+            .iter()
+            .filter_map(|usage| {
+                // Local usages are in the file declaring the symbol
+                let file = self
+                    .file_graph
+                    .node_weight(usage.file_id.unwrap_or(owner_index))?;
+                Some(Location::new(file.uri.clone(), usage.range))
+            })
+            .collect()
+    }
+
+    /// Describes the include graph of the workspace: every file, the includes resolved between
+    /// them, cycles included, and the includes that couldn't be resolved.
+    pub fn get_include_graph(&self) -> Value {
+        let files: Vec<&Url> = self
+            .file_graph
+            .node_weights()
+            .map(|file| &file.uri)
+            .collect();
+
+        let includes: Vec<Value> = self
+            .file_graph
+            .edge_references()
+            .map(|edge| {
+                let kind = match edge.weight() {
+                    Import::Local => "local",
+                    Import::Library => "library",
+                };
+                json!({
+                    "from": self.file_graph.node_weight(edge.source()).unwrap().uri,
+                    "to": self.file_graph.node_weight(edge.target()).unwrap().uri,
+                    "kind": kind,
+                })
+            })
+            .collect();
+
+        let unresolved: Vec<Value> = self
+            .file_graph
+            .node_weights()
+            .flat_map(|file| {
+                file.get_import_paths(&self.settings.include_paths)
+                    .into_iter()
+                    .filter_map(Result::err)
+                    .map(|(range, file_name)| {
+                        json!({ "from": file.uri, "include": file_name, "range": range })
+                    })
+            })
+            .collect();
+
+        json!({ "files": files, "includes": includes, "unresolved": unresolved })
+    }
+
+    /// Symbol table of the file rendered as text, one table per scope in source order.
+    pub fn get_symbol_table_text(&self, url: &Url) -> Option<String> {
+        let file = self.get_file(url)?;
+
+        Some(file.symbol_table_manager.lock().unwrap().to_string())
+    }
+
+    /// Number of symbols of each kind and of undefined usages, keyed by the uri of the file, for
+    /// the given file or every indexed one.
+    pub fn get_file_stats(&self, url: Option<&Url>) -> Value {
+        let stats: serde_json::Map<String, Value> = self
+            .file_graph
+            .node_weights()
+            .filter(|file| url.is_none_or(|url| *url == file.uri))
+            .map(|file| {
+                let mut kinds: BTreeMap<String, usize> = BTreeMap::new();
+                for symbol in file.symbol_table_manager.lock().unwrap().get_all_symbols() {
+                    *kinds.entry(symbol.kind).or_default() += 1;
+                }
+                let undefined = file
+                    .get_full_diagnostics()
+                    .into_iter()
+                    .filter(|diagnostic| {
+                        diagnostic.code == Some(NumberOrString::String(String::from("undefined")))
+                    })
+                    .count();
+
+                (
+                    file.uri.to_string(),
+                    json!({ "symbols": kinds, "undefined": undefined }),
+                )
+            })
+            .collect();
+
+        Value::Object(stats)
+    }
+
+    /// Marks a file as opened or closed in the editor. The content of open files is managed by
+    /// the editor, so they are never reloaded from disk.
+    pub fn set_file_open(&mut self, url: &Url, open: bool) {
+        if open {
+            self.open_files.insert(url.clone());
+        } else {
+            self.open_files.remove(url);
+        }
+    }
+
+    /// Reloads a file modified outside of the editor from disk, along with the files importing
+    /// it so that their links to its symbols stay valid. Returns the urls of the updated files.
+    pub fn reload_file(&mut self, url: &Url) -> Vec<Url> {
+        if self.open_files.contains(url) || !self.url_node_map.contains_key(url) {
+            return vec![];
+        }
+
+        let content = match url.to_file_path().map(fs::read_to_string) {
+            Ok(Ok(content)) => content,
+            _ => {
+                warn!("Failed to reload {url} from disk.");
+                return vec![];
+            }
+        };
+
+        info!("Reloading file: {url}");
+        self.replace_content(url, content)
+    }
+
+    /// Empties a file deleted outside of the editor, so that its symbols are gone, and updates the
+    /// files importing it, whose includes of it are now unresolved. Open files keep the content of
+    /// the editor. Returns the urls of the updated files.
+    pub fn remove_deleted_file(&mut self, url: &Url) -> Vec<Url> {
+        if self.open_files.contains(url) {
+            return vec![];
+        }
+
+        info!("Emptying deleted file: {url}");
+        self.replace_content(url, String::new())
+    }
+
+    // Replaces the whole content of the file, then updates its importers
+    fn replace_content(&mut self, url: &Url, content: String) -> Vec<Url> {
+        let Some(file_index) = self.url_node_map.get(url).copied() else {
+            return vec![];
+        };
+
+        self.update_file(
+            url,
+            vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: content,
+            }],
+        );
+
+        let mut updated = vec![url.clone()];
+        for importer_url in self.get_importers(file_index) {
+            self.update_file(&importer_url, vec![]);
+            updated.push(importer_url);
+        }
+
+        updated
+    }
+
+    /// Parses and translates a file again from scratch, reading it from disk unless it is open
+    /// in the editor. Returns false if the file isn't part of the workspace.
+    pub fn reparse_file(&mut self, url: &Url) -> bool {
+        let Some(file) = self.get_file(url) else {
+            return false;
+        };
+
+        let content = if self.open_files.contains(url) {
+            file.source_code.clone()
+        } else {
+            match url.to_file_path().map(fs::read_to_string) {
+                Ok(Ok(content)) => content,
+                _ => {
+                    warn!("Failed to read {url} from disk, reparsing its last content.");
+                    file.source_code.clone()
+                }
+            }
+        };
+
+        info!("Reparsing file: {url}");
+        // Replacing the whole text skips the incremental parsing and translation
+        self.update_file(
+            url,
+            vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: content,
+            }],
+        )
+        .is_some()
+    }
+
+    /// Names and kinds of the symbols a file exports, in order, to find out when they change.
+    pub fn get_exported_symbols(&self, url: &Url) -> Vec<(String, String)> {
+        let Some(file) = self.get_file(url) else {
+            return vec![];
+        };
+        let (symbols, _) = file
+            .symbol_table_manager
+            .lock()
+            .unwrap()
+            .get_symbols_at_root();
+
+        symbols
+            .into_iter()
+            .filter(|symbol| symbol.visibility == Visibility::Exported)
+            .map(|symbol| (symbol.name, symbol.kind))
+            .collect()
+    }
+
+    /// Links the files including `url`, directly or not, to its symbols again and computes their
+    /// full diagnostics. The closest `max_dependents_refreshed` files are refreshed.
+    pub fn refresh_dependents(&mut self, url: &Url) -> HashMap<Url, Vec<Diagnostic>> {
+        let Some(file_index) = self.url_node_map.get(url).copied() else {
+            return HashMap::new();
+        };
+
+        let dependents = get_dependents(
+            file_index,
+            |index| {
+                self.file_graph
+                    .neighbors_directed(index, EdgeDirection::Incoming)
+                    .collect()
+            },
+            self.settings.max_dependents_refreshed,
+        );
+        let urls: Vec<Url> = dependents
+            .into_iter()
+            .map(|index| self.file_graph[index].uri.clone())
+            .collect();
+
+        for dependent_url in &urls {
+            self.update_file(dependent_url, vec![]);
+        }
+        urls.into_iter()
+            .map(|dependent_url| {
+                let diagnostics = self.get_full_diagnostics(&dependent_url);
+                (dependent_url, diagnostics)
+            })
+            .collect()
+    }
+
+    fn get_importers(&self, file_index: NodeIndex) -> Vec<Url> {
+        let mut importers: Vec<Url> = vec![];
+
+        for edge in self
+            .file_graph
+            .edges_directed(file_index, EdgeDirection::Incoming)
+        {
+            let importer_url = &self.file_graph.node_weight(edge.source()).unwrap().uri;
+            if !importers.contains(importer_url) {
+                importers.push(importer_url.clone());
+            }
+        }
+
+        importers
+    }
+
     fn add_file(&mut self, url: &Url, content: &str) -> Option<NodeIndex> {
         if self.url_node_map.contains_key(url) {
             return None;
         }
 
-        let file = File::new(url.clone(), content, self.tree_sitter_language);
+        let file = File::new(
+            url.clone(),
+            content,
+            &self.parser_pool,
+            self.settings.max_file_size_bytes,
+        );
+        if content.len() > self.settings.max_file_size_bytes {
+            warn!(
+                "{url} is larger than {} bytes, it won't be indexed.",
+                self.settings.max_file_size_bytes
+            );
+        } else if !file.is_indexed() {
+            warn!("Failed to parse {url}, it won't be indexed.");
+        }
 
-        let import_paths = file.get_import_paths();
+        let import_paths = file.get_import_paths(&self.settings.include_paths);
         debug!("Resolved import paths: {:?}", import_paths);
 
         let new_file_index = self.file_graph.add_node(file);
@@ -91,8 +750,16 @@ impl Workspace {
 
         for path in import_paths {
             match path {
-                Ok((import_type, path)) => {
-                    let imported_file_url = Url::from_file_path(path.clone()).unwrap();
+                Ok((import_type, include, range)) => {
+                    report_include_resolution(url, range, &include);
+                    let path = include.path;
+                    let Ok(imported_file_url) = Url::from_file_path(&path) else {
+                        warn!(
+                            "Ignoring the include of {}, which isn't absolute",
+                            path.display()
+                        );
+                        continue;
+                    };
 
                     let maybe_imported_file_index = if let Some(imported_file_index) =
                         self.url_node_map.get(&imported_file_url)
@@ -101,8 +768,13 @@ impl Workspace {
                             .add_edge(new_file_index, *imported_file_index, import_type);
                         Some(*imported_file_index)
                     } else {
-                        let content = fs::read_to_string(path).unwrap();
-                        let imported_file_index = self.add_file(&imported_file_url, &content);
+                        let imported_file_index = match fs::read_to_string(&path) {
+                            Ok(content) => self.add_file(&imported_file_url, &content),
+                            Err(err) => {
+                                warn!("Failed to read {}: {err}", path.display());
+                                None
+                            }
+                        };
                         if let Some(i) = imported_file_index {
                             self.file_graph.add_edge(new_file_index, i, import_type);
                             Some(i)
@@ -115,17 +787,20 @@ impl Workspace {
                         self.link_imported_symbols(new_file_index, imported_file_index);
                     }
                 }
-                Err(range) => {
+                Err((range, file_name)) => {
                     info!("Import problem");
 
-                    super::features::diagnostics::ImportErrors::add_error(
+                    super::features::diagnostics::ImportErrors::add_unresolved_include(
                         url.clone(),
-                        Diagnostic::new_simple(range, String::from("File could not be found.")),
+                        range,
+                        &file_name,
                     );
                 }
             }
         }
 
+        self.link_imported_members(new_file_index);
+
         debug!("File graph:\n{:?}", Dot::with_config(&self.file_graph, &[]));
 
         Some(new_file_index)
@@ -149,6 +824,9 @@ impl Workspace {
             .collect();
 
         for (i, s) in imported_symbols.into_iter().enumerate() {
+            if s.visibility != Visibility::Exported {
+                continue;
+            }
             if let Some(range) = unlinked_symbols.get(&s.name) {
                 let symbol_id = SymbolId::new(Some(imported_file_index), scope_id, i);
 
@@ -163,18 +841,237 @@ impl Workspace {
         }
     }
 
+    /// Links what only resolves through included types: the types of the declarations typed by an
+    /// included type, then the member accesses on them, as in `hdr.ipv4.ttl` with `headers_t`
+    /// declared in an included file.
+    fn link_imported_members(&self, file_index: NodeIndex) {
+        let file = self.file_graph.node_weight(file_index).unwrap();
+
+        let (typed_declarations, member_ids) = {
+            let mut ast_manager = file.ast_manager.lock().unwrap();
+            let root = ast_manager.visit_root();
+            let typed_declarations: Vec<(SymbolId, SymbolId)> = root
+                .get_descendants()
+                .iter()
+                .filter_map(|node| get_imported_type(node))
+                .collect();
+
+            let arena = ast_manager.get_ast().get_arena_ref();
+            let member_ids: Vec<NodeId> = arena
+                .iter()
+                .filter(|node| {
+                    node.get().symbol == language_def::Symbol::MemberUsage
+                        && node.get().linked_symbol.is_none()
+                })
+                .filter_map(|node| arena.get_node_id(node))
+                .collect();
+
+            (typed_declarations, member_ids)
+        };
+
+        {
+            let mut symbol_table = file.symbol_table_manager.lock().unwrap();
+            for (symbol_id, type_id) in typed_declarations {
+                if let Some(symbol) = symbol_table.get_symbol_mut(symbol_id) {
+                    symbol.type_symbol.get_or_insert(type_id);
+                }
+            }
+        }
+
+        for member_id in member_ids {
+            // Looked up one at a time, the base of `ttl` in `hdr.ipv4.ttl` is linked just before
+            let Some((base_id, name, range)) = ({
+                let mut ast_manager = file.ast_manager.lock().unwrap();
+                let arena = ast_manager.get_ast().get_arena_ref();
+                get_member_base(arena, member_id).map(|base_id| {
+                    let member = arena.get(member_id).unwrap().get();
+                    (base_id, member.content.clone(), member.range)
+                })
+            }) else {
+                continue;
+            };
+
+            let Some((owner_index, field_id)) = find_member(
+                file_index,
+                &base_id,
+                &name,
+                |index, symbol_id| self.get_symbol(index, symbol_id),
+                |index, scope_id| {
+                    self.file_graph
+                        .node_weight(index)
+                        .map(|file| {
+                            file.symbol_table_manager
+                                .lock()
+                                .unwrap()
+                                .get_symbols_in_scope(scope_id)
+                        })
+                        .unwrap_or_default()
+                },
+            ) else {
+                continue;
+            };
+
+            let (linked_id, usage) = if owner_index == file_index {
+                (field_id.clone(), Usage::new_local(range))
+            } else {
+                (
+                    SymbolId::new(Some(owner_index), field_id.symbol_table_id, field_id.index),
+                    Usage::new_external(file_index, range),
+                )
+            };
+            file.ast_manager
+                .lock()
+                .unwrap()
+                .link_symbol(linked_id, range);
+            if let Some(field) = self.file_graph[owner_index]
+                .symbol_table_manager
+                .lock()
+                .unwrap()
+                .get_symbol_mut(field_id)
+            {
+                field.add_usage(usage);
+            }
+        }
+    }
+
+    /// Returns the symbol pointed to by `symbol_id` along with the index of the file defining it.
+    /// `file_index` is the file the id was found in, used when the id is local to that file.
+    fn get_symbol(
+        &self,
+        file_index: NodeIndex,
+        symbol_id: &SymbolId,
+    ) -> Option<(NodeIndex, Symbol)> {
+        let owner_index = symbol_id.get_file_id().unwrap_or(file_index);
+        let symbol = self
+            .file_graph
+            .node_weight(owner_index)?
+            .symbol_table_manager
+            .lock()
+            .unwrap()
+            .get_symbol(symbol_id.clone())?
+            .clone();
+
+        Some((owner_index, symbol))
+    }
+
+    /// Resolves the type `type_id` through its aliases (e.g. typedefs of typedefs) down to the
+    /// type it ultimately refers to.
+    fn resolve_type(
+        &self,
+        file_index: NodeIndex,
+        type_id: &SymbolId,
+    ) -> Option<(NodeIndex, Symbol)> {
+        follow_type_aliases(file_index, type_id, |index, id| self.get_symbol(index, id))
+    }
+
     fn clear_outgoing_edges(&mut self, file_index: NodeIndex) {
         let outgoing_edges: Vec<_> = self
             .file_graph
             .edges_directed(file_index, EdgeDirection::Outgoing)
-            .map(|edge| edge.id())
+            .map(|edge| (edge.id(), edge.target()))
             .collect();
 
-        for id in outgoing_edges {
+        for (id, imported_file_index) in outgoing_edges {
+            // Usages are added back when the imports are linked again
+            self.file_graph
+                .node_weight(imported_file_index)
+                .unwrap()
+                .symbol_table_manager
+                .lock()
+                .unwrap()
+                .remove_external_usages(file_index);
+
             self.file_graph.remove_edge(id);
         }
     }
 
+    /// Whether the file must not be edited: it matches one of the read-only globs, or it is a
+    /// library file such as the built-in headers.
+    fn is_readonly(&self, url: &Url) -> bool {
+        if let Ok(path) = url.to_file_path() {
+            if utils::matches_any_glob(
+                &self.settings.readonly_globs,
+                self.root_path.as_deref(),
+                &path,
+            ) {
+                return true;
+            }
+        }
+
+        self.url_node_map.get(url).is_some_and(|file_index| {
+            self.file_graph
+                .edges_directed(*file_index, EdgeDirection::Incoming)
+                .any(|edge| matches!(edge.weight(), Import::Library))
+        })
+    }
+
+    /// Hints on the local includes of the file none of whose symbols it uses, directly or through
+    /// the files they include. Files defining macros or that aren't indexed always count as used,
+    /// since their usages aren't all known.
+    fn get_unused_include_diagnostics(&self, url: &Url) -> Vec<Diagnostic> {
+        let Some(file_index) = self.url_node_map.get(url).copied() else {
+            return vec![];
+        };
+        let file = self.file_graph.node_weight(file_index).unwrap();
+        if !file.is_indexed() {
+            return vec![];
+        }
+
+        let macro_kinds: Vec<&String> = LanguageDefinition::get()
+            .macros
+            .iter()
+            .map(|macro_rule| &macro_rule.symbol_kind)
+            .collect();
+        let is_used = |included_index: NodeIndex| {
+            let included_file = self.file_graph.node_weight(included_index).unwrap();
+            !included_file.is_indexed()
+                || included_file
+                    .symbol_table_manager
+                    .lock()
+                    .unwrap()
+                    .get_all_symbols()
+                    .iter()
+                    .any(|symbol| {
+                        macro_kinds.contains(&&symbol.kind)
+                            || symbol
+                                .usages
+                                .iter()
+                                .any(|usage| usage.file_id == Some(file_index))
+                    })
+        };
+        let get_includes = |index: NodeIndex| {
+            self.file_graph
+                .neighbors_directed(index, EdgeDirection::Outgoing)
+                .collect()
+        };
+
+        file.get_import_paths(&self.settings.include_paths)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|(import_type, include, _)| {
+                matches!(import_type, Import::Local)
+                    && !utils::matches_any_glob(
+                        &self.settings.unused_include_ignores,
+                        self.root_path.as_deref(),
+                        &include.path,
+                    )
+            })
+            .filter_map(|(_, include, range)| {
+                let included_url = Url::from_file_path(&include.path).ok()?;
+                let included_index = *self.url_node_map.get(&included_url)?;
+                if is_include_used(included_index, get_includes, is_used) {
+                    return None;
+                }
+
+                let file_name = include.path.strip_prefix(&include.root).ok()?;
+                Some(diagnostics::unused_include(
+                    range,
+                    &file_name.to_string_lossy(),
+                ))
+            })
+            .collect()
+    }
+
     fn is_local_import(&self, file_index: NodeIndex, imported_file_index: NodeIndex) -> bool {
         let edge_index = self
             .file_graph
@@ -187,6 +1084,281 @@ impl Workspace {
     }
 }
 
+/// The declared symbol and the type of a declaration whose type is declared in another file.
+fn get_imported_type(node: &VisitNode) -> Option<(SymbolId, SymbolId)> {
+    let language_def::Symbol::Init {
+        name_node,
+        type_node: Some(type_node),
+        ..
+    } = &node.get().symbol
+    else {
+        return None;
+    };
+
+    let type_id = node
+        .get_child_of_kind(NodeKind::Node(type_node.clone()))?
+        .get_descendants()
+        .into_iter()
+        .find_map(|descendant| descendant.get().linked_symbol.clone())
+        .filter(|type_id| type_id.get_file_id().is_some())?;
+    let symbol_id = node
+        .get_child_of_kind(NodeKind::Node(name_node.clone()))?
+        .get()
+        .linked_symbol
+        .clone()?;
+
+    Some((symbol_id, type_id))
+}
+
+/// Symbol whose member is accessed: the usage before it, or the member before it in a chain.
+fn get_member_base(arena: &Arena<Node>, member_id: NodeId) -> Option<SymbolId> {
+    let previous_id = arena.get(member_id)?.previous_sibling()?;
+    let previous = arena.get(previous_id)?.get();
+
+    match previous.symbol {
+        language_def::Symbol::Usage => previous.linked_symbol.clone(),
+        language_def::Symbol::Expression => previous_id.children(arena).find_map(|id| {
+            let node = arena.get(id)?.get();
+            if node.symbol == language_def::Symbol::MemberUsage {
+                node.linked_symbol.clone()
+            } else {
+                None
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// Location of the declaration of `symbol` first, then of the other candidates with its name and
+/// kind.
+fn get_overloads(symbol: &Symbol, candidates: Vec<Symbol>) -> Vec<Location> {
+    let mut locations = vec![symbol.get_location()];
+    for candidate in candidates {
+        let location = candidate.get_location();
+        if candidate.name == symbol.name
+            && candidate.kind == symbol.kind
+            && !locations.contains(&location)
+        {
+            locations.push(location);
+        }
+    }
+
+    locations
+}
+
+/// Whether another symbol of the scope is already named `new_name`.
+fn is_name_taken(new_name: &str, symbol: &Symbol, scope_symbols: &[Symbol]) -> bool {
+    scope_symbols
+        .iter()
+        .any(|other| other.name == new_name && other.def_range != symbol.def_range)
+}
+
+/// Edits renaming the declaration of the symbol, in the file at `declaring_url`, and all of its
+/// usages. `get_url` returns the url of the file of an external usage.
+fn get_rename_edits(
+    symbol: &Symbol,
+    declaring_url: &Url,
+    new_name: &str,
+    get_url: impl Fn(NodeIndex) -> Url,
+) -> HashMap<Url, Vec<TextEdit>> {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::from([(
+        declaring_url.clone(),
+        vec![TextEdit::new(symbol.def_range, new_name.to_string())],
+    )]);
+
+    for usage in &symbol.usages {
+        let usage_url = usage
+            .file_id
+            .map_or_else(|| declaring_url.clone(), &get_url);
+        changes
+            .entry(usage_url)
+            .or_default()
+            .push(TextEdit::new(usage.range, new_name.to_string()));
+    }
+
+    changes
+}
+
+/// Files including the file at `file_index`, directly or not, closest first and at most `limit`.
+/// `get_importers` returns the files directly including a file.
+fn get_dependents(
+    file_index: NodeIndex,
+    get_importers: impl Fn(NodeIndex) -> Vec<NodeIndex>,
+    limit: usize,
+) -> Vec<NodeIndex> {
+    let mut dependents: Vec<NodeIndex> = vec![];
+    let mut to_visit = VecDeque::from([file_index]);
+
+    while let Some(index) = to_visit.pop_front() {
+        for importer in get_importers(index) {
+            if dependents.len() == limit {
+                return dependents;
+            }
+            if importer != file_index && !dependents.contains(&importer) {
+                dependents.push(importer);
+                to_visit.push_back(importer);
+            }
+        }
+    }
+
+    dependents
+}
+
+/// First result of `find` over the file at `file_index` and the files it includes transitively,
+/// breadth-first so that the nearest files are searched first. Files including each other are
+/// searched once. `get_includes` returns the files included by a file.
+fn find_nearest<T>(
+    file_index: NodeIndex,
+    get_includes: impl Fn(NodeIndex) -> Vec<NodeIndex>,
+    find: impl Fn(NodeIndex) -> Option<T>,
+) -> Option<T> {
+    let mut visited = HashSet::from([file_index]);
+    let mut to_visit = VecDeque::from([file_index]);
+
+    while let Some(index) = to_visit.pop_front() {
+        if let Some(found) = find(index) {
+            return Some(found);
+        }
+        for include in get_includes(index) {
+            if visited.insert(include) {
+                to_visit.push_back(include);
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether the file at `included_index`, or one of the files it includes transitively, is used
+/// according to `is_used`. `get_includes` returns the files included by a file.
+fn is_include_used(
+    included_index: NodeIndex,
+    get_includes: impl Fn(NodeIndex) -> Vec<NodeIndex>,
+    is_used: impl Fn(NodeIndex) -> bool,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut to_visit = vec![included_index];
+
+    while let Some(index) = to_visit.pop() {
+        if !visited.insert(index) {
+            continue;
+        }
+        if is_used(index) {
+            return true;
+        }
+        to_visit.extend(get_includes(index));
+    }
+
+    false
+}
+
+/// Finds the field named `member` in the type of the symbol `base_id`, found in the file at
+/// `file_index`. Returns the file declaring the field and its id in that file. `lookup` finds
+/// symbols like `follow_type_aliases`, and `get_scope` returns the symbols of a file's scope.
+fn find_member(
+    file_index: NodeIndex,
+    base_id: &SymbolId,
+    member: &str,
+    lookup: impl Fn(NodeIndex, &SymbolId) -> Option<(NodeIndex, Symbol)>,
+    get_scope: impl Fn(NodeIndex, ScopeId) -> Vec<Symbol>,
+) -> Option<(NodeIndex, SymbolId)> {
+    let (base_file_index, base) = lookup(file_index, base_id)?;
+    // Types like an enum or `error` are their own container
+    let (type_file_index, type_symbol) = match &base.type_symbol {
+        Some(type_id) => follow_type_aliases(base_file_index, type_id, &lookup)?,
+        None => (base_file_index, base),
+    };
+
+    let field_scope_id = type_symbol.field_scope_id?;
+    let index = get_scope(type_file_index, field_scope_id)
+        .iter()
+        .position(|field| field.name == member)?;
+
+    Some((type_file_index, SymbolId::new(None, field_scope_id, index)))
+}
+
+/// Follows the `type_symbol` links starting at `type_id` until reaching a type that doesn't alias
+/// another one. Returns `None` if a symbol can't be found or if the aliases form a cycle.
+fn follow_type_aliases(
+    file_index: NodeIndex,
+    type_id: &SymbolId,
+    lookup: impl Fn(NodeIndex, &SymbolId) -> Option<(NodeIndex, Symbol)>,
+) -> Option<(NodeIndex, Symbol)> {
+    let mut visited = vec![(
+        type_id.get_file_id().unwrap_or(file_index),
+        type_id.symbol_table_id,
+        type_id.index,
+    )];
+    let (mut current_file_index, mut current) = lookup(file_index, type_id)?;
+
+    while let Some(next_id) = current.type_symbol.clone() {
+        let key = (
+            next_id.get_file_id().unwrap_or(current_file_index),
+            next_id.symbol_table_id,
+            next_id.index,
+        );
+        if visited.contains(&key) {
+            warn!("Type alias cycle detected while resolving {}", current.name);
+            return None;
+        }
+        visited.push(key);
+
+        (current_file_index, current) = lookup(current_file_index, &next_id)?;
+    }
+
+    Some((current_file_index, current))
+}
+
+/// Makes the include paths relative to the workspace root absolute, skipping them without a root.
+fn resolve_include_paths(include_paths: Vec<PathBuf>, root_path: Option<&Path>) -> Vec<PathBuf> {
+    include_paths
+        .into_iter()
+        .filter_map(|path| match root_path {
+            _ if path.is_absolute() => Some(path),
+            Some(root_path) => Some(root_path.join(path)),
+            None => {
+                warn!(
+                    "Ignoring the include path {}, relative without a workspace root",
+                    path.display()
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Logs the root an include was found in, and notes the later roots it shadows.
+fn report_include_resolution(url: &Url, range: Range, include: &ResolvedInclude) {
+    debug!(
+        "Include {} resolved in {}",
+        include.path.display(),
+        include.root.display()
+    );
+
+    if include.shadowed.is_empty() {
+        return;
+    }
+
+    super::features::diagnostics::ImportErrors::add_error(
+        url.clone(),
+        Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::INFORMATION),
+            message: format!(
+                "Resolved in {}, shadowing the file in {}.",
+                include.root.display(),
+                include
+                    .shadowed
+                    .iter()
+                    .map(|root| root.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            ..Default::default()
+        },
+    );
+}
+
 impl FileManagement for Workspace {
     fn get_file(&self, url: &Url) -> Option<&File> {
         let index = self.url_node_map.get(url)?;
@@ -202,19 +1374,39 @@ impl FileManagement for Workspace {
         self.add_file(&url, content);
     }
 
-    fn update_file(&mut self, url: &Url, changes: Vec<TextDocumentContentChangeEvent>) {
+    /// Applies the changes to a file of the workspace, returning None if it isn't part of it.
+    fn update_file(
+        &mut self,
+        url: &Url,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> Option<()> {
+        let Some(file_index) = self.url_node_map.get(url).copied() else {
+            warn!("Ignoring changes to {url}, which isn't part of the workspace.");
+            return None;
+        };
         super::features::diagnostics::ImportErrors::clear(url);
-        let file_index = *self.url_node_map.get(url).unwrap();
         self.clear_outgoing_edges(file_index);
 
-        let file = self.get_file_mut(url).unwrap();
+        let file = self.file_graph.node_weight_mut(file_index).unwrap();
 
-        file.update(changes);
+        file.update(
+            changes,
+            &self.parser_pool,
+            self.settings.max_file_size_bytes,
+        );
 
-        for path in file.get_import_paths() {
+        for path in file.get_import_paths(&self.settings.include_paths) {
             match path {
-                Ok((import_type, path)) => {
-                    let imported_file_url = Url::from_file_path(path.clone()).unwrap();
+                Ok((import_type, include, range)) => {
+                    report_include_resolution(url, range, &include);
+                    let path = include.path;
+                    let Ok(imported_file_url) = Url::from_file_path(&path) else {
+                        warn!(
+                            "Ignoring the include of {}, which isn't absolute",
+                            path.display()
+                        );
+                        continue;
+                    };
 
                     let maybe_imported_file_index = if let Some(imported_file_index) =
                         self.url_node_map.get(&imported_file_url)
@@ -223,8 +1415,13 @@ impl FileManagement for Workspace {
                             .add_edge(file_index, *imported_file_index, import_type);
                         Some(*imported_file_index)
                     } else {
-                        let content = fs::read_to_string(path).unwrap();
-                        let imported_file_index = self.add_file(&imported_file_url, &content);
+                        let imported_file_index = match fs::read_to_string(&path) {
+                            Ok(content) => self.add_file(&imported_file_url, &content),
+                            Err(err) => {
+                                warn!("Failed to read {}: {err}", path.display());
+                                None
+                            }
+                        };
                         if let Some(i) = imported_file_index {
                             self.file_graph.add_edge(file_index, i, import_type);
                             Some(i)
@@ -237,56 +1434,128 @@ impl FileManagement for Workspace {
                         self.link_imported_symbols(file_index, imported_file_index);
                     }
                 }
-                Err(range) => {
-                    super::features::diagnostics::ImportErrors::add_error(
+                Err((range, file_name)) => {
+                    info!("Import problem");
+
+                    super::features::diagnostics::ImportErrors::add_unresolved_include(
                         url.clone(),
-                        Diagnostic::new_simple(range, String::from("File could not be found.")),
+                        range,
+                        &file_name,
                     );
                 }
             }
         }
 
+        self.link_imported_members(file_index);
+
         debug!("File graph:\n{:?}", Dot::with_config(&self.file_graph, &[]));
+
+        Some(())
     }
 }
 
 impl LanguageActions for Workspace {
-    fn get_definition_location(&self, url: &Url, symbol_position: Position) -> Option<Location> {
+    fn get_declaration_location(&self, url: &Url, symbol_position: Position) -> Option<Location> {
         let file = self.get_file(url)?;
 
         let ast_query = file.ast_manager.lock().unwrap();
         let root_visit = ast_query.visit_root();
         let node = root_visit.get_node_at_position(symbol_position)?;
 
-        debug!("Goto def of: {:?}", node.get());
+        debug!("Goto declaration of: {:?}", node.get());
         let symbol_id = node.get().linked_symbol.clone()?;
 
-        Some(if let Some(file_index) = symbol_id.get_file_id() {
-            let other_file = self.file_graph.node_weight(file_index).unwrap();
-            let range = other_file
+        let (_, symbol) = self.get_symbol(*self.url_node_map.get(url)?, &symbol_id)?;
+        Some(symbol.get_location())
+    }
+
+    // Implementations aren't linked to their declarations yet, so the definitions are the
+    // declarations: the linked one, then the others of the same name visible alongside it
+    fn get_definition_locations(&self, url: &Url, symbol_position: Position) -> Vec<Location> {
+        let Some(file_index) = self.url_node_map.get(url).copied() else {
+            return vec![];
+        };
+        let Some(file) = self.get_file(url) else {
+            return vec![];
+        };
+        let Some(symbol_id) = file.get_symbol_id_at_pos(symbol_position) else {
+            // Usages left unlinked are looked up in the nearest file declaring their name
+            let Some(name) = file.get_unlinked_usage_name(symbol_position) else {
+                return vec![];
+            };
+            return self.find_nearest_declaration(file_index, &name);
+        };
+        let Some((owner_index, symbol)) = self.get_symbol(file_index, &symbol_id) else {
+            return vec![];
+        };
+        let scope_id = symbol_id.symbol_table_id;
+
+        let owner = self.file_graph.node_weight(owner_index).unwrap();
+        let (_, root_id) = owner
+            .symbol_table_manager
+            .lock()
+            .unwrap()
+            .get_symbols_at_root();
+        if scope_id != root_id {
+            let candidates = owner
                 .symbol_table_manager
                 .lock()
                 .unwrap()
-                .get_symbol(symbol_id)?
-                .def_range;
+                .get_symbols_in_scope(scope_id);
+            return get_overloads(&symbol, candidates);
+        }
 
-            Location {
-                uri: other_file.uri.clone(),
-                range,
-            }
-        } else {
-            let range = file
+        // Top-level declarations can be repeated in any of the files included along the way,
+        // the nearest come first
+        let mut candidates = vec![];
+        let mut bfs = Bfs::new(&self.file_graph, file_index);
+        while let Some(index) = bfs.next(&self.file_graph) {
+            let (symbols, _) = self
+                .file_graph
+                .node_weight(index)
+                .unwrap()
                 .symbol_table_manager
                 .lock()
                 .unwrap()
-                .get_symbol(symbol_id)?
-                .def_range;
+                .get_symbols_at_root();
+            candidates.extend(symbols.into_iter().filter(|candidate| {
+                index == file_index || candidate.visibility == Visibility::Exported
+            }));
+        }
+        get_overloads(&symbol, candidates)
+    }
 
-            Location {
-                uri: url.clone(),
-                range,
-            }
-        })
+    fn get_type_definition_location(
+        &self,
+        url: &Url,
+        symbol_position: Position,
+    ) -> Option<Location> {
+        let file_index = *self.url_node_map.get(url)?;
+        let file = self.get_file(url)?;
+
+        let symbol_id = file.get_symbol_id_at_pos(symbol_position)?;
+        let (symbol_file_index, symbol) = self.get_symbol(file_index, &symbol_id)?;
+        let (_, type_symbol) = self.resolve_type(symbol_file_index, &symbol.type_symbol?)?;
+
+        Some(type_symbol.get_location())
+    }
+
+    // Only symbols declared in files that can be edited, which excludes the library includes
+    fn prepare_rename(&self, url: &Url, position: Position) -> Option<Range> {
+        let file_index = *self.url_node_map.get(url)?;
+        let (range, symbol_id) = {
+            let ast_query = self.get_file(url)?.ast_manager.lock().unwrap();
+            let root_visit = ast_query.visit_root();
+            let node = root_visit.get_node_at_position(position)?;
+            (node.get().range, node.get().linked_symbol.clone()?)
+        };
+
+        let (owner_index, _) = self.get_symbol(file_index, &symbol_id)?;
+        if self.is_readonly(&self.file_graph[owner_index].uri) {
+            return None;
+        }
+
+        Some(range)
     }
 
     fn rename_symbol(
@@ -295,7 +1564,7 @@ impl LanguageActions for Workspace {
         symbol_position: Position,
         new_name: String,
     ) -> Option<WorkspaceEdit> {
-        let file = self.get_file_mut(url).unwrap();
+        let file = self.get_file_mut(url)?;
 
         let symbol_id = file
             .ast_manager
@@ -307,62 +1576,50 @@ impl LanguageActions for Workspace {
             .linked_symbol
             .clone()?;
 
-        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
-        let symbol = if let Some(file_id) = symbol_id.file_id {
-            if !self.is_local_import(*self.url_node_map.get(url)?, file_id) {
+        let file_index = *self.url_node_map.get(url)?;
+        if let Some(file_id) = symbol_id.file_id {
+            if !self.is_local_import(file_index, file_id) {
                 // You should not edit files that are not local to the project
                 return None;
             }
-
-            let file = self.file_graph.node_weight(file_id).unwrap();
-            let s = file
-                .symbol_table_manager
-                .lock()
-                .unwrap()
-                .get_symbol(symbol_id.clone())?
-                .clone();
-            changes.insert(
-                file.uri.clone(),
-                vec![TextEdit::new(s.def_range, new_name.clone())],
-            );
-            s
-        } else {
-            let s = file
-                .symbol_table_manager
-                .lock()
-                .unwrap()
-                .get_symbol(symbol_id.clone())?
-                .clone();
-            changes.insert(
-                url.clone(),
-                vec![TextEdit::new(s.def_range, new_name.clone())],
-            );
-            s
+        }
+        let owner_index = symbol_id.file_id.unwrap_or(file_index);
+        let owner = self.file_graph.node_weight(owner_index)?;
+        let (symbol, scope_symbols) = {
+            let st_query = owner.symbol_table_manager.lock().unwrap();
+            (
+                st_query.get_symbol(symbol_id.clone())?.clone(),
+                st_query.get_symbols_in_scope(symbol_id.symbol_table_id),
+            )
         };
 
+        if is_name_taken(&new_name, &symbol, &scope_symbols) {
+            warn!("Rename refused: {new_name} is already declared in the scope.");
+            return None;
+        }
+
         debug!("{:?}", symbol.usages);
-        for usage in symbol.usages {
-            if let Some(file_id) = usage.file_id {
-                let file_url = &self.file_graph.node_weight(file_id).unwrap().uri;
-                let file_edits = changes.entry(file_url.clone()).or_default();
-                file_edits.push(TextEdit::new(usage.range, new_name.clone()));
-            } else if let Some(file_id) = symbol_id.file_id {
-                let file_url = &self.file_graph.node_weight(file_id).unwrap().uri;
-                let file_edits = changes.entry(file_url.clone()).or_default();
-                file_edits.push(TextEdit::new(usage.range, new_name.clone()));
-            } else {
-                let file_edits = changes.entry(url.clone()).or_default();
-                file_edits.push(TextEdit::new(usage.range, new_name.clone()));
-            }
+        let changes = get_rename_edits(&symbol, &owner.uri, &new_name, |file_id| {
+            self.file_graph.node_weight(file_id).unwrap().uri.clone()
+        });
+
+        // A partial rename would leave the read-only files pointing to the old name
+        if let Some(readonly_url) = changes.keys().find(|url| self.is_readonly(url)) {
+            warn!("Rename refused: {readonly_url} is read-only.");
+            return None;
         }
 
         Some(WorkspaceEdit::new(changes))
     }
 
-    fn get_semantic_tokens(&self, url: &Url) -> Option<SemanticTokensResult> {
+    fn get_semantic_tokens(
+        &self,
+        url: &Url,
+        extra_tokens: Vec<ColorData>,
+    ) -> Option<SemanticTokensResult> {
         let file = self.get_file(url)?;
 
-        Some(file.get_semantic_tokens())
+        Some(file.get_semantic_tokens(extra_tokens))
     }
 
     fn get_completion(
@@ -370,69 +1627,129 @@ impl LanguageActions for Workspace {
         url: &Url,
         position: Position,
         context: Option<CompletionContext>,
-    ) -> Option<Vec<CompletionItem>> {
-        let file_index = *self.url_node_map.get(url).unwrap();
-        let file = self.get_file(url)?;
+        plugin_items: Vec<CompletionItem>,
+    ) -> Option<CompletionList> {
+        let items = completion::merge_plugin_items(
+            self.get_completion_items(url, position, context)?,
+            plugin_items,
+        );
+        let prefix = self.get_file(url)?.get_word_prefix(position);
+
+        Some(completion::limit_list(items, prefix))
+    }
 
-        if context.is_none()
-            || context.clone().unwrap().trigger_kind == CompletionTriggerKind::INVOKED
-        {
-            if let Some(mut items) = file.get_completion_list(position, context) {
-                for edge in self
-                    .file_graph
-                    .edges_directed(file_index, EdgeDirection::Outgoing)
-                {
-                    let imported_file = self.file_graph.node_weight(edge.target()).unwrap();
-                    items.append(&mut imported_file.get_import_completion_list());
-                }
-                Some(items)
-            } else {
-                None
-            }
-        } else {
-            file.get_completion_list(position, context)
+    fn get_hover_info(&self, url: &Url, position: Position) -> Option<HoverContents> {
+        let contents = self.get_symbol_hover_info(url, position);
+        if !self.settings.debug_hover {
+            return contents;
         }
+
+        let node = self.get_file(url)?.get_syntax_node(position)?;
+        Some(hover::append_node_info(contents, &node))
     }
 
-    fn get_hover_info(&self, url: &Url, position: Position) -> Option<HoverContents> {
-        let file = self.get_file(url)?;
+    fn get_references(
+        &self,
+        url: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let (declaration, usages) = self.find_symbol_occurrences(url, position)?;
+
+        Some(references::with_declaration(
+            declaration,
+            usages,
+            include_declaration,
+        ))
+    }
 
-        if let Some(symbol_id) = file.get_symbol_id_at_pos(position) {
-            let symbol = {
-                let st = if let Some(file_id) = symbol_id.get_file_id() {
-                    self.file_graph
-                        .node_weight(file_id)
-                        .unwrap()
-                        .symbol_table_manager
-                        .lock()
-                        .unwrap()
-                } else {
-                    file.symbol_table_manager.lock().unwrap()
-                };
+    fn get_document_highlights(
+        &self,
+        url: &Url,
+        position: Position,
+    ) -> Option<Vec<DocumentHighlight>> {
+        let (declaration, usages) = self.find_symbol_occurrences(url, position)?;
 
-                st.get_symbol(symbol_id)?.clone()
-            };
+        Some(references::highlights(url, declaration, usages))
+    }
 
-            let st = if let Some(file_id) = symbol.type_symbol.clone()?.get_file_id() {
-                self.file_graph
-                    .node_weight(file_id)
-                    .unwrap()
-                    .symbol_table_manager
-                    .lock()
-                    .unwrap()
-            } else {
-                file.symbol_table_manager.lock().unwrap()
-            };
+    fn get_inlay_hints(&self, url: &Url, range: Range) -> Option<Vec<InlayHint>> {
+        let file_index = *self.url_node_map.get(url)?;
+        let file = self.file_graph.node_weight(file_index)?;
 
-            let type_symbol = st.get_symbol(symbol.type_symbol.clone()?)?;
+        let hints = file
+            .get_calls(range)
+            .into_iter()
+            .filter_map(|call| {
+                let parameters: Vec<String> = self
+                    .get_parameters(file_index, &call.callee, &call.rule.parameter_kind)?
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                Some(inlay_hints::parameter_hints(&call.arguments, &parameters))
+            })
+            .flatten()
+            .collect();
 
-            Some(HoverContents::Scalar(MarkedString::String(format!(
-                "{}: {}",
-                symbol.name, type_symbol.name
-            ))))
-        } else {
-            None
+        Some(hints)
+    }
+
+    fn get_signature_help(&self, url: &Url, position: Position) -> Option<SignatureHelp> {
+        let file_index = *self.url_node_map.get(url)?;
+        let (call, active_parameter) = self.get_file(url)?.get_call_at(position)?;
+        let (_, callee) = self.get_symbol(file_index, &call.callee)?;
+        let parameters =
+            self.get_parameters(file_index, &call.callee, &call.rule.parameter_kind)?;
+
+        Some(signature_help::build(
+            &callee.name,
+            &parameters,
+            active_parameter,
+        ))
+    }
+
+    fn get_code_actions(&self, url: &Url, range: Range) -> Option<CodeActionResponse> {
+        let comment = LanguageDefinition::get().line_comment.as_ref()?;
+        let file = self.get_file(url)?;
+
+        let edits = code_actions::toggle_line_comments(&file.source_code, range, comment);
+        if edits.is_empty() {
+            return Some(vec![]);
         }
+
+        Some(vec![CodeActionOrCommand::CodeAction(CodeAction {
+            title: String::from("Toggle line comment"),
+            kind: Some(CodeActionKind::REFACTOR),
+            edit: Some(WorkspaceEdit::new(HashMap::from([(url.clone(), edits)]))),
+            ..Default::default()
+        })])
+    }
+
+    fn get_workspace_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let symbols = self
+            .file_graph
+            .node_weights()
+            .filter_map(|file| self.file_symbols(&file.uri))
+            .flatten()
+            .collect();
+
+        workspace_symbols::search(symbols, query)
+    }
+
+    fn get_document_symbols(
+        &self,
+        url: &Url,
+        hierarchical: bool,
+    ) -> Option<DocumentSymbolResponse> {
+        let file = self.get_file(url)?;
+
+        Some(file.get_document_symbols(hierarchical))
+    }
+
+    fn get_folding_ranges(&self, url: &Url) -> Vec<FoldingRange> {
+        self.get_file(url)
+            .map(|file| file.get_folding_ranges())
+            .unwrap_or_default()
     }
 
     fn get_quick_diagnostics(&self, url: &Url) -> Vec<Diagnostic> {
@@ -449,9 +1766,294 @@ impl LanguageActions for Workspace {
         let maybe_file = self.get_file(url);
 
         if let Some(file) = maybe_file {
-            file.get_full_diagnostics()
+            let mut diagnostics = file.get_full_diagnostics();
+            diagnostics.append(&mut self.get_unused_include_diagnostics(url));
+            diagnostics.append(&mut diagnostics::check_naming_conventions(
+                &file.symbol_table_manager.lock().unwrap().get_all_symbols(),
+                &self.naming_conventions,
+            ));
+            diagnostics
         } else {
             vec![]
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use indextree::Arena;
+    use petgraph::prelude::NodeIndex;
+    use tower_lsp::lsp_types::{Position, Range, Url};
+
+    use super::{
+        find_member, find_nearest, follow_type_aliases, get_dependents, get_overloads,
+        get_rename_edits, is_include_used, is_name_taken, resolve_include_paths, FileManagement,
+        LanguageActions, Workspace,
+    };
+    use crate::language_def;
+    use crate::project::metadata::test_utils::{self, range};
+    use crate::project::metadata::{Symbol, SymbolId, Usage};
+    use crate::project::test_language;
+
+    fn symbol(name: &str, line: u32, type_symbol: Option<SymbolId>) -> Symbol {
+        Symbol {
+            type_symbol,
+            ..test_utils::symbol(name, "Type", range(line, 0, 1))
+        }
+    }
+
+    #[test]
+    fn test_untitled_document() {
+        language_def::load_test_definition();
+        let mut workspace = Workspace::new(test_language::language());
+        let url = Url::parse("untitled:Untitled-1").unwrap();
+        FileManagement::add_file(&mut workspace, url.clone(), "control c\n  ap");
+
+        // Nothing is declared, so the keywords of the language are offered
+        let completion = workspace
+            .get_completion(&url, Position::new(1, 4), None, vec![])
+            .unwrap();
+        let labels: Vec<_> = completion.items.iter().map(|item| &item.label).collect();
+        assert_eq!(labels, ["control", "apply"]);
+
+        let file = workspace.get_file(&url).unwrap();
+        assert!(file.is_indexed());
+        assert_eq!(format!("{file:?}"), "File { name: \"Untitled-1\", .. }");
+    }
+
+    #[test]
+    fn test_overloaded_definitions() {
+        // extern Checksum { Checksum(); Checksum(bit<16> seed); }
+        let mut constructor = symbol("Checksum", 1, None);
+        constructor.kind = String::from("Action");
+        let mut overload = symbol("Checksum", 2, None);
+        overload.kind = String::from("Action");
+        let type_named_alike = symbol("Checksum", 0, None);
+
+        let locations = get_overloads(
+            &constructor,
+            vec![type_named_alike, constructor.clone(), overload.clone()],
+        );
+
+        assert_eq!(
+            locations,
+            vec![constructor.get_location(), overload.get_location()]
+        );
+    }
+
+    #[test]
+    fn test_unused_include() {
+        // main.p4 includes headers.p4, used, lib.p4, which includes itself and the used util.p4,
+        // and unused.p4
+        let [headers, lib, util, unused] = [1, 2, 3, 4].map(NodeIndex::new);
+        let get_includes = |index: NodeIndex| {
+            if index == lib {
+                vec![util, lib]
+            } else {
+                vec![]
+            }
+        };
+        let is_used = |index: NodeIndex| index == headers || index == util;
+
+        assert!(is_include_used(headers, get_includes, is_used));
+        assert!(is_include_used(lib, get_includes, is_used));
+        assert!(!is_include_used(unused, get_includes, is_used));
+    }
+
+    #[test]
+    fn test_rename_constant_used_three_times() {
+        let declaring_url = Url::parse("file:///constants.p4").unwrap();
+        let mut constant = symbol("MAX", 0, None);
+        constant.add_usage(Usage::new_local(Range::new(
+            Position::new(2, 4),
+            Position::new(2, 7),
+        )));
+        constant.add_usage(Usage::new_local(Range::new(
+            Position::new(3, 4),
+            Position::new(3, 7),
+        )));
+        constant.add_usage(Usage::new_external(
+            NodeIndex::new(1),
+            Range::new(Position::new(5, 0), Position::new(5, 3)),
+        ));
+
+        let changes = get_rename_edits(&constant, &declaring_url, "LIMIT", |_| {
+            Url::parse("file:///main.p4").unwrap()
+        });
+        let edit_count: usize = changes.values().map(Vec::len).sum();
+        assert_eq!(edit_count, 4);
+        assert_eq!(changes[&declaring_url].len(), 3);
+        assert!(changes[&declaring_url]
+            .iter()
+            .all(|edit| edit.new_text == "LIMIT"));
+
+        let others = [constant.clone(), symbol("LIMIT", 1, None)];
+        assert!(is_name_taken("LIMIT", &constant, &others));
+        assert!(!is_name_taken("MAX", &constant, &others));
+        assert!(!is_name_taken("SIZE", &constant, &others));
+    }
+
+    #[test]
+    fn test_dependents_of_edited_header() {
+        // headers.p4 is included by parser.p4 and main.p4, main.p4 also includes parser.p4 and
+        // test.p4 includes main.p4
+        let [headers, parser, main, test] = [0, 1, 2, 3].map(NodeIndex::new);
+        let get_importers = |index: NodeIndex| {
+            if index == headers {
+                vec![parser, main]
+            } else if index == parser {
+                vec![main]
+            } else if index == main {
+                vec![test]
+            } else {
+                vec![]
+            }
+        };
+
+        assert_eq!(
+            get_dependents(headers, get_importers, 10),
+            vec![parser, main, test]
+        );
+        assert_eq!(
+            get_dependents(headers, get_importers, 2),
+            vec![parser, main]
+        );
+        assert!(get_dependents(test, get_importers, 10).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_included_declaration() {
+        // main.p4 includes v1model.p4 and lib.p4, which includes core.p4, and core.p4 includes
+        // lib.p4 back. Both v1model.p4 and core.p4 declare the name
+        let [main, v1model, lib, core] = [0, 1, 2, 3].map(NodeIndex::new);
+        let get_includes = |index: NodeIndex| {
+            if index == main {
+                vec![lib, v1model]
+            } else if index == lib {
+                vec![core]
+            } else if index == core {
+                vec![lib]
+            } else {
+                vec![]
+            }
+        };
+        let find = |index: NodeIndex| (index == v1model || index == core).then_some(index);
+
+        assert_eq!(find_nearest(main, get_includes, find), Some(v1model));
+        assert_eq!(find_nearest(lib, get_includes, find), Some(core));
+        assert_eq!(find_nearest(lib, get_includes, |_| None::<()>), None);
+    }
+
+    #[test]
+    fn test_find_member_of_included_type() {
+        let mut scopes = Arena::<()>::new();
+        let root = scopes.new_node(());
+        let headers_fields = scopes.new_node(());
+        let ipv4_fields = scopes.new_node(());
+        let header = NodeIndex::new(0);
+        let main = NodeIndex::new(1);
+
+        // header.p4: struct headers_t { ipv4_t ipv4; } header ipv4_t { bit<8> ttl; }
+        //            header other_t { bit<8> ttl; }
+        let with_fields = |name: &str, line, fields| {
+            let mut symbol = symbol(name, line, None);
+            symbol.field_scope_id = Some(fields);
+            symbol
+        };
+        let header_root = [
+            with_fields("headers_t", 0, headers_fields),
+            with_fields("ipv4_t", 1, ipv4_fields),
+            with_fields("other_t", 2, scopes.new_node(())),
+        ];
+        let header_fields = [symbol("ipv4", 0, Some(SymbolId::new(None, root, 1)))];
+        let ipv4_t_fields = [symbol("ttl", 1, None)];
+        // main.p4: headers_t hdr; ... hdr.ipv4.ttl
+        let hdr = symbol("hdr", 5, Some(SymbolId::new(Some(header), root, 0)));
+
+        let lookup = |index: NodeIndex, symbol_id: &SymbolId| {
+            let owner = symbol_id.get_file_id().unwrap_or(index);
+            let symbols: &[Symbol] = match (owner.index(), symbol_id.symbol_table_id) {
+                (1, _) => std::slice::from_ref(&hdr),
+                (_, scope) if scope == root => &header_root,
+                (_, scope) if scope == headers_fields => &header_fields,
+                _ => &ipv4_t_fields,
+            };
+            Some((owner, symbols.get(symbol_id.index)?.clone()))
+        };
+        let get_scope = |index: NodeIndex, scope_id| {
+            assert_eq!(index, header);
+            if scope_id == headers_fields {
+                header_fields.to_vec()
+            } else if scope_id == ipv4_fields {
+                ipv4_t_fields.to_vec()
+            } else {
+                vec![]
+            }
+        };
+        let hdr_id = SymbolId::new(None, root, 0);
+
+        let (owner, ipv4_id) = find_member(main, &hdr_id, "ipv4", lookup, get_scope).unwrap();
+        assert_eq!(
+            (owner, &ipv4_id),
+            (header, &SymbolId::new(None, headers_fields, 0))
+        );
+
+        // The field ids are relative to header.p4, as linked from main.p4
+        let ipv4_id = SymbolId::new(Some(owner), ipv4_id.symbol_table_id, ipv4_id.index);
+        let (owner, ttl_id) = find_member(main, &ipv4_id, "ttl", lookup, get_scope).unwrap();
+        assert_eq!(
+            (owner, ttl_id),
+            (header, SymbolId::new(None, ipv4_fields, 0))
+        );
+
+        assert!(find_member(main, &hdr_id, "ttl", lookup, get_scope).is_none());
+    }
+
+    #[test]
+    fn test_follow_type_aliases() {
+        let scope_id = Arena::<()>::new().new_node(());
+        let file_index = NodeIndex::new(0);
+        let id = |index| SymbolId::new(None, scope_id, index);
+
+        // typedef bit<8> Inner; typedef Inner Outer;
+        let symbols = [
+            symbol("bit<8>", 0, None),
+            symbol("Inner", 1, Some(id(0))),
+            symbol("Outer", 2, Some(id(1))),
+        ];
+        let lookup =
+            |index, symbol_id: &SymbolId| Some((index, symbols.get(symbol_id.index)?.clone()));
+
+        let (_, resolved) = follow_type_aliases(file_index, &id(2), lookup).unwrap();
+        assert_eq!(resolved.name, "bit<8>");
+
+        let (_, resolved) = follow_type_aliases(file_index, &id(0), lookup).unwrap();
+        assert_eq!(resolved.name, "bit<8>");
+
+        // typedef B A; typedef A B;
+        let cyclic = [symbol("A", 0, Some(id(1))), symbol("B", 1, Some(id(0)))];
+        let lookup =
+            |index, symbol_id: &SymbolId| Some((index, cyclic.get(symbol_id.index)?.clone()));
+
+        assert!(follow_type_aliases(file_index, &id(0), lookup).is_none());
+    }
+
+    #[test]
+    fn test_resolve_include_paths() {
+        let include_paths = vec![PathBuf::from("/opt/p4include"), PathBuf::from("include")];
+
+        assert_eq!(
+            resolve_include_paths(include_paths.clone(), Some(Path::new("/home/user/project"))),
+            vec![
+                PathBuf::from("/opt/p4include"),
+                PathBuf::from("/home/user/project/include")
+            ]
+        );
+        assert_eq!(
+            resolve_include_paths(include_paths, None),
+            vec![PathBuf::from("/opt/p4include")]
+        );
+    }
+}
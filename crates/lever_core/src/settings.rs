@@ -1,16 +1,147 @@
-use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
-#[derive(Debug, Default)]
-pub struct Settings {}
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Files larger than this aren't parsed or indexed by default (10 MiB).
+const DEFAULT_MAX_FILE_SIZE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Settings sent by the client, every missing field keeps its default value.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub max_file_size_bytes: usize,
+    // Whether symbols of library imports are offered in completion
+    pub completion_include_builtins: bool,
+    // Whether hovers also show the syntax node under the cursor, to debug grammars
+    pub debug_hover: bool,
+    // Files matching these globs, absolute or relative to the workspace root, are never edited
+    pub readonly_globs: Vec<String>,
+    // Whether files that aren't open get quick diagnostics as soon as they are included
+    pub eager_diagnostics: bool,
+    // Included files matching these globs are never reported as unused, library includes never are
+    pub unused_include_ignores: Vec<String>,
+    // Regex the names of the symbols of each kind must match, kinds without one aren't checked
+    pub naming_conventions: BTreeMap<String, String>,
+    // Files including an edited file, directly or not, whose diagnostics are refreshed per edit
+    pub max_dependents_refreshed: usize,
+    // Directories searched for includes before the library paths of the language, absolute or
+    // relative to the workspace root
+    pub include_paths: Vec<PathBuf>,
+    // Directories whose JSON files list plugins, loaded along with the initialization options
+    pub plugin_paths: Vec<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            max_file_size_bytes: DEFAULT_MAX_FILE_SIZE_BYTES,
+            completion_include_builtins: true,
+            debug_hover: false,
+            readonly_globs: vec![],
+            eager_diagnostics: false,
+            unused_include_ignores: vec![],
+            naming_conventions: BTreeMap::new(),
+            max_dependents_refreshed: 50,
+            include_paths: vec![],
+            plugin_paths: vec![],
+        }
+    }
+}
 
 impl Settings {
+    /// Parses the settings field by field, an invalid value only resets its own field to the
+    /// default.
     pub fn parse(value: Value) -> Settings {
-        if let Value::Object(_map) = value {
-            Settings {}
-        } else {
-            Settings {
-                // ..Default::default()
+        for key in unknown_keys(&value) {
+            warn!("Unknown setting: {key}");
+        }
+
+        let Value::Object(values) = value else {
+            return Settings::default();
+        };
+        let Some(mut fields) = default_fields() else {
+            return Settings::default();
+        };
+
+        for (key, value) in values {
+            if !fields.contains_key(&key) {
+                continue;
+            }
+
+            let mut candidate = fields.clone();
+            candidate.insert(key.clone(), value);
+            match serde_json::from_value::<Settings>(Value::Object(candidate.clone())) {
+                Ok(_) => fields = candidate,
+                Err(err) => warn!("Invalid setting {key}, using the default: {err}"),
             }
         }
+
+        serde_json::from_value(Value::Object(fields)).unwrap_or_default()
+    }
+}
+
+// Values of the fields of the default settings, by name
+fn default_fields() -> Option<Map<String, Value>> {
+    match serde_json::to_value(Settings::default()) {
+        Ok(Value::Object(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+// Keys of the object that aren't fields of the settings, most likely typos
+fn unknown_keys(value: &Value) -> Vec<String> {
+    let Some(fields) = default_fields() else {
+        return vec![];
+    };
+
+    value
+        .as_object()
+        .into_iter()
+        .flat_map(|map| map.keys())
+        .filter(|key| !fields.contains_key(*key))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::{unknown_keys, Settings};
+
+    #[test]
+    fn test_unknown_settings() {
+        let value = json!({ "debug_hover": true, "max_file_size": 1024 });
+
+        assert_eq!(unknown_keys(&value), vec![String::from("max_file_size")]);
+        assert_eq!(
+            Settings::parse(value),
+            Settings {
+                debug_hover: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_setting() {
+        let value = json!({
+            "debug_hover": true,
+            "max_file_size_bytes": "1 MiB",
+            "include_paths": ["/opt/p4include"],
+        });
+
+        assert_eq!(
+            Settings::parse(value),
+            Settings {
+                debug_hover: true,
+                include_paths: vec![PathBuf::from("/opt/p4include")],
+                ..Default::default()
+            }
+        );
     }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use tower_lsp::lsp_types::Position;
 use tree_sitter::Point;
@@ -34,46 +34,171 @@ pub fn pos_to_byte(pos: Position, text: &str) -> usize {
     total_bytes + pos.character as usize
 }
 
+/// Byte offset in the line of the UTF-16 `character` offset of a position, None past its end.
+pub fn utf16_to_byte(line: &str, character: u32) -> Option<usize> {
+    let character = character as usize;
+    let mut utf16_offset = 0;
+    for (byte, c) in line.char_indices() {
+        if utf16_offset >= character {
+            return Some(byte);
+        }
+        utf16_offset += c.len_utf16();
+    }
+
+    (utf16_offset >= character).then_some(line.len())
+}
+
+/// Point at the end of `new_content` once inserted at `start`.
 pub fn calculate_end_point(start: Point, new_content: &str) -> Point {
-    let new_lines: Vec<&str> = new_content.lines().collect();
-    let nb_lines = if new_lines.is_empty() {
-        1
-    } else {
-        new_lines.len()
-    };
+    match new_content.rfind('\n') {
+        None => Point {
+            row: start.row,
+            column: start.column + new_content.len(),
+        },
+        Some(last_line_break) => Point {
+            row: start.row + new_content.matches('\n').count(),
+            column: new_content.len() - last_line_break - 1,
+        },
+    }
+}
 
-    let column = match new_lines.len() {
-        0 => start.column,
-        1 => start.column + new_content.len(),
-        _ => new_lines.last().unwrap().len(),
+/// Identifier characters directly before `byte` in the text.
+pub fn get_word_before(text: &str, byte: usize) -> &str {
+    let Some(before) = text.get(..byte) else {
+        return "";
     };
+    let word_len: usize = before
+        .chars()
+        .rev()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .map(char::len_utf8)
+        .sum();
 
-    Point {
-        column,
-        row: start.row + nb_lines,
+    &before[before.len() - word_len..]
+}
+
+/// Value of a string literal without its quotes and with its escape sequences replaced, or None
+/// if the text isn't a string literal.
+pub fn unescape_string_literal(text: &str) -> Option<String> {
+    let inner = text.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            // An unescaped quote ends the literal before the end of the text
+            return None;
+        }
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            'r' => value.push('\r'),
+            '0' => value.push('\0'),
+            escaped => value.push(escaped),
+        }
     }
+
+    Some(value)
+}
+
+/// Name and argument of an annotation like `@name("ingress.drop")`. An argument that is a
+/// string literal is unescaped.
+pub fn parse_annotation(content: &str) -> Option<(&str, String)> {
+    let (name, rest) = content.trim().strip_prefix('@')?.split_once('(')?;
+    let argument = rest.strip_suffix(')')?.trim();
+
+    Some((
+        name.trim(),
+        unescape_string_literal(argument).unwrap_or_else(|| argument.to_string()),
+    ))
+}
+
+/// Text of the line up to `byte`.
+pub fn get_line_before(text: &str, byte: usize) -> &str {
+    let Some(before) = text.get(..byte) else {
+        return "";
+    };
+
+    before.rfind('\n').map_or(before, |i| &before[i + 1..])
 }
 
 pub fn get_node_text(node: &tree_sitter::Node, source_code: &str) -> String {
     node.utf8_text(source_code.as_bytes()).unwrap().to_string()
 }
 
-pub fn find_lib(paths: &[PathBuf], file_name: &str) -> Option<PathBuf> {
-    paths.iter().find_map(|path| {
-        let mut path = path.clone();
-        path.push(file_name);
-        if path.exists() {
-            return Some(path);
-        }
-        None
+#[derive(Debug, PartialEq, Eq)]
+pub struct ResolvedInclude {
+    pub path: PathBuf,
+    // Root in which the file was found
+    pub root: PathBuf,
+    // Later roots also containing the file
+    pub shadowed: Vec<PathBuf>,
+}
+
+/// Looks for `file_name` in each root in order, the first root containing it wins.
+pub fn resolve_include(roots: &[PathBuf], file_name: &str) -> Option<ResolvedInclude> {
+    let mut matching_roots = roots.iter().filter(|root| root.join(file_name).exists());
+
+    let root = matching_roots.next()?;
+    Some(ResolvedInclude {
+        path: root.join(file_name),
+        root: root.clone(),
+        shadowed: matching_roots.cloned().collect(),
     })
 }
 
+/// Whether the path matches one of the globs, either as is or relative to the root.
+pub fn matches_any_glob(globs: &[String], root: Option<&Path>, path: &Path) -> bool {
+    let absolute = path.to_string_lossy();
+    let relative = root
+        .and_then(|root| path.strip_prefix(root).ok())
+        .map(|relative| relative.to_string_lossy());
+
+    globs.iter().any(|glob| {
+        glob_match(glob.as_bytes(), absolute.as_bytes())
+            || relative
+                .as_ref()
+                .is_some_and(|relative| glob_match(glob.as_bytes(), relative.as_bytes()))
+    })
+}
+
+// `*` and `?` don't match `/`, `**` matches any number of directories
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|i| *i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match(rest, &text[i..])),
+        [b'?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, text)| *c != b'/' && glob_match(rest, text)),
+        [c, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(t, text)| t == c && glob_match(rest, text)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
     use tower_lsp::lsp_types::Position;
 
-    use super::pos_to_byte;
+    use tree_sitter::Point;
+
+    use super::{
+        calculate_end_point, matches_any_glob, parse_annotation, pos_to_byte, resolve_include,
+        unescape_string_literal,
+    };
 
     #[test]
     fn test_pos_to_byte() {
@@ -100,4 +225,88 @@ mod tests {
             5
         );
     }
+
+    #[test]
+    fn test_include_precedence() {
+        let temp_dir = std::env::temp_dir().join(format!("lever-includes-{}", std::process::id()));
+        let local = temp_dir.join("local");
+        let system = temp_dir.join("system");
+        fs::create_dir_all(&local).unwrap();
+        fs::create_dir_all(&system).unwrap();
+        fs::write(local.join("core.p4"), "").unwrap();
+        fs::write(system.join("core.p4"), "").unwrap();
+        fs::write(system.join("v1model.p4"), "").unwrap();
+
+        let local_include = resolve_include(&[local.clone(), system.clone()], "core.p4").unwrap();
+        assert_eq!(local_include.path, local.join("core.p4"));
+        assert_eq!(local_include.root, local);
+        assert_eq!(local_include.shadowed, vec![system.clone()]);
+
+        let system_include = resolve_include(std::slice::from_ref(&system), "core.p4").unwrap();
+        assert_eq!(system_include.root, system);
+        assert!(system_include.shadowed.is_empty());
+
+        let fallback = resolve_include(&[local.clone(), system.clone()], "v1model.p4").unwrap();
+        assert_eq!(fallback.root, system);
+
+        assert!(resolve_include(&[local, system], "missing.p4").is_none());
+
+        fs::remove_dir_all(temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_calculate_end_point() {
+        let start = Point { row: 3, column: 4 };
+
+        assert_eq!(calculate_end_point(start, ""), start);
+        assert_eq!(
+            calculate_end_point(start, "drop;"),
+            Point { row: 3, column: 9 }
+        );
+        assert_eq!(
+            calculate_end_point(start, "{\n    drop;\n}"),
+            Point { row: 5, column: 1 }
+        );
+        assert_eq!(
+            calculate_end_point(start, "drop;\n"),
+            Point { row: 4, column: 0 }
+        );
+    }
+
+    #[test]
+    fn test_unescape_string_literal() {
+        assert_eq!(
+            unescape_string_literal(r#""a\"b\\c""#),
+            Some(String::from(r#"a"b\c"#))
+        );
+        // An inner quote ends the literal early, a trailing backslash escapes the closing quote
+        assert_eq!(unescape_string_literal(r#""a"b""#), None);
+        assert_eq!(unescape_string_literal(r#""ab\""#), None);
+
+        assert_eq!(
+            parse_annotation(r#"@name("ingress.\"drop\"")"#),
+            Some(("name", String::from(r#"ingress."drop""#)))
+        );
+        assert_eq!(
+            parse_annotation(r#"@name("a"b")"#),
+            Some(("name", String::from(r#""a"b""#)))
+        );
+    }
+
+    #[test]
+    fn test_readonly_globs() {
+        let globs = vec![
+            String::from("generated/**"),
+            String::from("/opt/p4/**/*.p4"),
+        ];
+        let root = PathBuf::from("/home/user/project");
+
+        let readonly = |path: &str| matches_any_glob(&globs, Some(&root), Path::new(path));
+
+        assert!(readonly("/home/user/project/generated/headers.p4"));
+        assert!(readonly("/home/user/project/generated/v1/parser.p4"));
+        assert!(readonly("/opt/p4/include/core.p4"));
+        assert!(!readonly("/home/user/project/main.p4"));
+        assert!(!readonly("/opt/p4/include/core.txt"));
+    }
 }
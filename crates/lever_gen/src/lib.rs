@@ -194,12 +194,17 @@ fn gen_query(query: &TreesitterNodeQuery) -> proc_macro2::TokenStream {
             if path.is_empty() {
                 panic!("Empty paths are not allowed.");
             }
+            // Reported as a compile error where the translator is generated, rather than a panic
+            if path
+                .iter()
+                .any(|query| matches!(query, TreesitterNodeQuery::Path(_)))
+            {
+                return quote!(compile_error!("Nested paths are not supported."));
+            }
 
             let mut parts = vec![];
             parts.push(match path.first().unwrap() {
-                TreesitterNodeQuery::Path(_) => {
-                    unimplemented!("Nested paths are not supported.")
-                }
+                TreesitterNodeQuery::Path(_) => unreachable!("Nested paths were rejected above."),
                 TreesitterNodeQuery::Kind(kind) => quote!(child_by_kind(node, #kind)),
                 TreesitterNodeQuery::Field(field) => quote!(node.child_by_field_name(#field)),
             });
@@ -207,7 +212,7 @@ fn gen_query(query: &TreesitterNodeQuery) -> proc_macro2::TokenStream {
             for query in path.iter().skip(1) {
                 parts.push(match query {
                     TreesitterNodeQuery::Path(_) => {
-                        unimplemented!("Nested paths are not supported.")
+                        unreachable!("Nested paths were rejected above.")
                     }
                     TreesitterNodeQuery::Kind(kind) => quote!(
                         .and_then(|n| child_by_kind(node, #kind))